@@ -0,0 +1,72 @@
+// Ping-pong between two threads over a pair of channels, comparing the wait strategies on round trip latency. Every message finds the other side waiting on an empty queue, which is exactly the case the strategies differ in. Spinning only pays off with a core to spare for each side, on a single core it just gets in the way of the thread it's waiting for
+// Run with `cargo bench --bench latency`
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use mpsc::{Builder, WaitStrategy};
+
+const ROUND_TRIPS: usize = 20_000;
+const RUNS: usize = 5;
+
+fn run(strategy: WaitStrategy) -> Vec<Duration> {
+    let (ping_tx, mut ping_rx) = Builder::new().wait_strategy(strategy).build();
+    let (pong_tx, mut pong_rx) = Builder::new().wait_strategy(strategy).build();
+
+    let echo = thread::spawn(move || {
+        while let Ok(i) = ping_rx.recv() {
+            pong_tx.send(i).unwrap();
+        }
+    });
+
+    let mut round_trips = Vec::with_capacity(ROUND_TRIPS);
+    for i in 0..ROUND_TRIPS {
+        let start = Instant::now();
+        ping_tx.send(i).unwrap();
+        assert_eq!(pong_rx.recv(), Ok(i));
+        round_trips.push(start.elapsed());
+    }
+
+    drop(ping_tx);
+    echo.join().unwrap();
+    round_trips
+}
+
+fn report(name: &str, strategy: WaitStrategy) {
+    let mut medians = Vec::new();
+    let mut p99s = Vec::new();
+    for _ in 0..RUNS {
+        let mut round_trips = run(strategy);
+        round_trips.sort();
+        medians.push(round_trips[round_trips.len() / 2]);
+        p99s.push(round_trips[round_trips.len() * 99 / 100]);
+    }
+    medians.sort();
+    p99s.sort();
+    println!(
+        "{name:<16} median {:>10.2?}  p99 {:>10.2?}",
+        medians[RUNS / 2],
+        p99s[RUNS / 2]
+    );
+}
+
+fn main() {
+    println!("{ROUND_TRIPS} round trips, median of {RUNS} runs");
+    report("park", WaitStrategy::Park);
+    report(
+        "spin/yield/park",
+        WaitStrategy::SpinThenYieldThenPark {
+            spins: 1_000,
+            yields: 10,
+        },
+    );
+
+    // Two threads spinning on a single core just take turns burning their time slices
+    let cores = thread::available_parallelism().map_or(1, |n| n.get());
+    if cores >= 2 {
+        report("busy spin", WaitStrategy::BusySpin);
+    } else {
+        println!("busy spin        skipped, needs at least 2 cores");
+    }
+}
@@ -0,0 +1,61 @@
+// Compares the mutex based channel against the lock-free one with many producers hammering a single consumer
+// Run with `cargo bench --bench throughput`
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+const PRODUCERS: usize = 16;
+const MESSAGES_PER_PRODUCER: usize = 100_000;
+const RUNS: usize = 5;
+
+// Both flavours have the same API but no common trait, so the benchmark body is a macro
+macro_rules! run {
+    ($channel:path) => {{
+        let (tx, mut rx) = $channel();
+        let start = Instant::now();
+
+        let producers: Vec<_> = (0..PRODUCERS)
+            .map(|_| {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    for i in 0..MESSAGES_PER_PRODUCER {
+                        tx.send(i).unwrap();
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+
+        let mut received = 0;
+        while rx.recv().is_ok() {
+            received += 1;
+        }
+        let elapsed = start.elapsed();
+
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        assert_eq!(received, PRODUCERS * MESSAGES_PER_PRODUCER);
+        elapsed
+    }};
+}
+
+fn report(name: &str, mut runs: Vec<Duration>) {
+    runs.sort();
+    let median = runs[runs.len() / 2];
+    let total = (PRODUCERS * MESSAGES_PER_PRODUCER) as f64;
+    println!(
+        "{name:<10} median {median:>10.2?}  {:>6.2} M msgs/s",
+        total / median.as_secs_f64() / 1_000_000.0
+    );
+}
+
+fn main() {
+    println!("{PRODUCERS} producers x {MESSAGES_PER_PRODUCER} messages, median of {RUNS} runs");
+    report("mutex", (0..RUNS).map(|_| run!(mpsc::channel)).collect());
+    report(
+        "lockfree",
+        (0..RUNS).map(|_| run!(mpsc::lockfree::channel)).collect(),
+    );
+}
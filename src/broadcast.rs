@@ -0,0 +1,232 @@
+// Broadcast channel: every reciever sees every message. Messages live in a ring buffer shared by all recievers, each of which only keeps a cursor into it. Senders never wait, once the ring is full the oldest message is overwritten and recievers that hadn't seen it yet get a Lagged error telling them how many messages they missed
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fmt,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::SendError;
+
+// Sender
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        let mut inner = self.shared.lock();
+        inner.senders_count += 1;
+
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut inner = self.shared.lock();
+        inner.senders_count -= 1;
+        if inner.senders_count == 0 {
+            drop(inner);
+            // Every subscriber may be waiting, not just one
+            self.shared.available.notify_all();
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    // Returns how many recievers the message was sent to. Fails (handing the message back) if there are none
+    pub fn send(&self, data: T) -> Result<usize, SendError<T>> {
+        let mut channel = self.shared.lock();
+        if channel.receivers_count == 0 {
+            return Err(SendError::Disconnected(data));
+        }
+
+        channel.ring.push_back(data);
+        let mut overwritten = None;
+        if channel.ring.len() > channel.capacity {
+            // Overwrite the oldest message. Recievers that still pointed at it will notice through their cursor
+            overwritten = channel.ring.pop_front();
+            channel.head += 1;
+        }
+        let receivers_count = channel.receivers_count;
+        drop(channel);
+        self.shared.available.notify_all();
+        // Its Drop may panic, so only once the channel is consistent again and unlocked
+        drop(overwritten);
+        Ok(receivers_count)
+    }
+
+    // Creates a new reciever that sees every message sent from now on
+    pub fn subscribe(&self) -> Reciever<T> {
+        let mut inner = self.shared.lock();
+        inner.receivers_count += 1;
+        let next = inner.head + inner.ring.len() as u64;
+
+        Reciever {
+            shared: Arc::clone(&self.shared),
+            next,
+        }
+    }
+}
+
+// Reciever
+pub struct Reciever<T> {
+    shared: Arc<Shared<T>>,
+    next: u64, // Sequence number of the next message this reciever wants to see
+}
+
+// A clone starts off at the same position as the original
+impl<T> Clone for Reciever<T> {
+    fn clone(&self) -> Self {
+        let mut inner = self.shared.lock();
+        inner.receivers_count += 1;
+
+        Self {
+            shared: Arc::clone(&self.shared),
+            next: self.next,
+        }
+    }
+}
+
+impl<T> Drop for Reciever<T> {
+    fn drop(&mut self) {
+        let mut inner = self.shared.lock();
+        inner.receivers_count -= 1;
+    }
+}
+
+impl<T: Clone> Reciever<T> {
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        let mut channel = self.shared.lock();
+        loop {
+            match channel.read(&mut self.next) {
+                Err(TryRecvError::Empty) => {
+                    channel = self
+                        .shared
+                        .available
+                        .wait(channel)
+                        .unwrap_or_else(PoisonError::into_inner);
+                }
+                Err(TryRecvError::Lagged(missed)) => return Err(RecvError::Lagged(missed)),
+                Err(TryRecvError::Closed) => return Err(RecvError::Closed),
+                Ok(val) => return Ok(val),
+            }
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let channel = self.shared.lock();
+        channel.read(&mut self.next)
+    }
+}
+
+// Channel
+struct Shared<T> {
+    inner: Mutex<Inner<T>>,
+    available: Condvar,
+}
+
+impl<T> Shared<T> {
+    // The only code of the user's that runs under the lock is T::clone() in Inner::read(), which can't leave Inner half-updated since the cursor it moves belongs to the reciever. So a poisoned lock is still good to use, and Drop must never panic on it
+    fn lock(&self) -> MutexGuard<'_, Inner<T>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+struct Inner<T> {
+    ring: VecDeque<T>,
+    head: u64, // Sequence number of the oldest message in the ring
+    capacity: usize,
+    senders_count: usize,
+    receivers_count: usize,
+}
+
+impl<T: Clone> Inner<T> {
+    // `next` is the cursor of the reciever we are reading for
+    fn read(&self, next: &mut u64) -> Result<T, TryRecvError> {
+        if *next < self.head {
+            // We've been overrun. Skip ahead to the oldest message still around, the next recv() picks up from there
+            let missed = self.head - *next;
+            *next = self.head;
+            return Err(TryRecvError::Lagged(missed));
+        }
+
+        match self.ring.get((*next - self.head) as usize) {
+            Some(val) => {
+                *next += 1;
+                Ok(val.clone())
+            }
+            None if self.senders_count == 0 => Err(TryRecvError::Closed),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+}
+
+// Errors
+// `Lagged` means the reciever was too slow and that many messages were overwritten before it got to them. The reciever is still usable, the next recv() returns the oldest message still in the ring
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum RecvError {
+    Lagged(u64),
+    Closed,
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvError::Lagged(missed) => write!(f, "reciever lagged behind by {missed} messages"),
+            RecvError::Closed => "receiving on a closed channel".fmt(f),
+        }
+    }
+}
+
+impl Error for RecvError {}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TryRecvError {
+    Empty,
+    Lagged(u64),
+    Closed,
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryRecvError::Empty => "receiving on an empty channel".fmt(f),
+            TryRecvError::Lagged(missed) => {
+                write!(f, "reciever lagged behind by {missed} messages")
+            }
+            TryRecvError::Closed => "receiving on a closed channel".fmt(f),
+        }
+    }
+}
+
+impl Error for TryRecvError {}
+
+// Each reciever can fall at most `capacity` messages behind before it starts missing some
+pub fn channel<T: Clone>(capacity: usize) -> (Sender<T>, Reciever<T>) {
+    assert!(capacity > 0, "broadcast channel capacity must be non-zero");
+
+    let inner = Inner {
+        ring: VecDeque::with_capacity(capacity),
+        head: 0,
+        capacity,
+        senders_count: 1,
+        receivers_count: 1,
+    };
+
+    let shared = Arc::new(Shared {
+        inner: Mutex::new(inner),
+        available: Condvar::new(),
+    });
+
+    let sender = Sender {
+        shared: shared.clone(),
+    };
+
+    let receiver = Reciever { shared, next: 0 };
+
+    (sender, receiver)
+}
@@ -0,0 +1,58 @@
+use crate::{SendError, Sender};
+
+// Producer side counterpart of the reciever's buffer. Messages are collected locally and handed to the channel with send_all() once `threshold` of them have piled up, so that a busy producer takes the lock and notifies the reciever once per batch instead of once per message. Whatever is left is flushed on drop. A threshold of usize::MAX means only flushing when asked to
+//
+// The channel is only looked at when flushing, so a closed channel shows up as an error from the send() that fills the batch (or from flush()), not from the first send() after it was closed
+pub struct BufferedSender<T> {
+    sender: Sender<T>,
+    buffer: Vec<T>,
+    threshold: usize,
+}
+
+impl<T> Drop for BufferedSender<T> {
+    fn drop(&mut self) {
+        // Nobody left to hand the error to, so whatever couldn't be sent goes to the channel's dead letter hook like any other undelivered message. Without a hook it's lost
+        if let Err(err) = self.flush() {
+            if let Some(dead_letter) = &self.sender.shared.dead_letter {
+                err.into_inner().into_iter().for_each(dead_letter);
+            }
+        }
+    }
+}
+
+impl<T> BufferedSender<T> {
+    // A threshold of 0 or 1 sends every message right away
+    pub fn new(sender: Sender<T>, threshold: usize) -> Self {
+        BufferedSender {
+            sender,
+            buffer: Vec::new(),
+            threshold: threshold.max(1),
+        }
+    }
+
+    // Queues `data` locally and flushes once the batch is full. On error, the whole batch that couldn't be sent (including `data`) is handed back
+    pub fn send(&mut self, data: T) -> Result<(), SendError<Vec<T>>> {
+        self.buffer.push(data);
+        if self.buffer.len() >= self.threshold {
+            return self.flush();
+        }
+        Ok(())
+    }
+
+    // Sends whatever has been collected so far. Blocks like send_all() if the channel is bounded and full
+    pub fn flush(&mut self) -> Result<(), SendError<Vec<T>>> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        self.sender.send_all(std::mem::take(&mut self.buffer))
+    }
+
+    // Number of messages waiting for the next flush
+    pub fn pending(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn get_ref(&self) -> &Sender<T> {
+        &self.sender
+    }
+}
@@ -0,0 +1,204 @@
+use std::{error::Error, fmt, time::Duration};
+
+// Returned by send() when the message couldn't be sent. Either way the message is handed back so that it isn't lost and the caller can reroute it
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum SendError<T> {
+    // The reciever is gone or the channel has been closed
+    Disconnected(T),
+    // Some thread panicked while holding the channel's lock, and the channel was built with PoisonPolicy::Fail
+    Poisoned(T),
+    // The channel is bounded and full, and was built with OverflowPolicy::Fail
+    Full(T),
+}
+
+impl<T> SendError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            SendError::Disconnected(data) | SendError::Poisoned(data) | SendError::Full(data) => {
+                data
+            }
+        }
+    }
+
+    // Same error, different payload. For when a single failed message turns into a whole batch that wasn't sent
+    pub(crate) fn map<U>(self, f: impl FnOnce(T) -> U) -> SendError<U> {
+        match self {
+            SendError::Disconnected(data) => SendError::Disconnected(f(data)),
+            SendError::Poisoned(data) => SendError::Poisoned(f(data)),
+            SendError::Full(data) => SendError::Full(f(data)),
+        }
+    }
+}
+
+// Manual impl so that SendError<T> is Debug (and hence an Error) even when T isn't
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Disconnected(_) => f.write_str("Disconnected(..)"),
+            SendError::Poisoned(_) => f.write_str("Poisoned(..)"),
+            SendError::Full(_) => f.write_str("Full(..)"),
+        }
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Disconnected(_) => "sending on a closed channel".fmt(f),
+            SendError::Poisoned(_) => "sending on a poisoned channel".fmt(f),
+            SendError::Full(_) => "sending on a full channel".fmt(f),
+        }
+    }
+}
+
+impl<T> Error for SendError<T> {}
+
+// Returned by RateLimitedSender::send(). The message is handed back either way, same as with SendError
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum RateLimitError<T> {
+    // Out of tokens, and the sender was set to RateLimitMode::Reject
+    RateLimited(T),
+    // Out of tokens, and the sender was set to RateLimitMode::RetryAfter. Holds how long until the next token comes in
+    RetryAfter(T, Duration),
+    // Got a token, but the channel itself refused the message
+    Send(SendError<T>),
+}
+
+impl<T> RateLimitError<T> {
+    pub fn into_inner(self) -> T {
+        match self {
+            RateLimitError::RateLimited(data) | RateLimitError::RetryAfter(data, _) => data,
+            RateLimitError::Send(err) => err.into_inner(),
+        }
+    }
+}
+
+impl<T> From<SendError<T>> for RateLimitError<T> {
+    fn from(err: SendError<T>) -> Self {
+        RateLimitError::Send(err)
+    }
+}
+
+impl<T> fmt::Debug for RateLimitError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RateLimitError::RateLimited(_) => f.write_str("RateLimited(..)"),
+            RateLimitError::RetryAfter(_, wait) => write!(f, "RetryAfter(.., {wait:?})"),
+            RateLimitError::Send(err) => write!(f, "Send({err:?})"),
+        }
+    }
+}
+
+impl<T> fmt::Display for RateLimitError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RateLimitError::RateLimited(_) => "sending faster than the rate limit".fmt(f),
+            RateLimitError::RetryAfter(_, wait) => {
+                write!(f, "sending faster than the rate limit, retry in {wait:?}")
+            }
+            RateLimitError::Send(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<T> Error for RateLimitError<T> {}
+
+// Returned by recv() once every sender is gone and the channel has been drained, or if the channel is poisoned (see SendError::Poisoned)
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum RecvError {
+    Disconnected,
+    Poisoned,
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvError::Disconnected => "receiving on a closed channel".fmt(f),
+            RecvError::Poisoned => "receiving on a poisoned channel".fmt(f),
+        }
+    }
+}
+
+impl Error for RecvError {}
+
+// Returned by try_recv(). `Empty` means senders are still around and more data may come later
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TryRecvError {
+    Empty,
+    Disconnected,
+    Poisoned,
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryRecvError::Empty => "receiving on an empty channel".fmt(f),
+            TryRecvError::Disconnected => "receiving on a closed channel".fmt(f),
+            TryRecvError::Poisoned => "receiving on a poisoned channel".fmt(f),
+        }
+    }
+}
+
+impl Error for TryRecvError {}
+
+impl From<RecvError> for TryRecvError {
+    fn from(err: RecvError) -> Self {
+        match err {
+            RecvError::Disconnected => TryRecvError::Disconnected,
+            RecvError::Poisoned => TryRecvError::Poisoned,
+        }
+    }
+}
+
+// Returned by recv_timeout() and recv_deadline()
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum RecvTimeoutError {
+    Timeout,
+    Disconnected,
+    Poisoned,
+}
+
+impl fmt::Display for RecvTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvTimeoutError::Timeout => "timed out waiting on channel".fmt(f),
+            RecvTimeoutError::Disconnected => "channel is empty and sending half is closed".fmt(f),
+            RecvTimeoutError::Poisoned => "receiving on a poisoned channel".fmt(f),
+        }
+    }
+}
+
+impl Error for RecvTimeoutError {}
+
+impl From<RecvError> for RecvTimeoutError {
+    fn from(err: RecvError) -> Self {
+        match err {
+            RecvError::Disconnected => RecvTimeoutError::Disconnected,
+            RecvError::Poisoned => RecvTimeoutError::Poisoned,
+        }
+    }
+}
+
+// Returned by Select::try_ready() when none of the recievers is ready
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct TrySelectError;
+
+impl fmt::Display for TrySelectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "all operations in select would block".fmt(f)
+    }
+}
+
+impl Error for TrySelectError {}
+
+// Returned by Select::ready_timeout() and ready_deadline()
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct SelectTimeoutError;
+
+impl fmt::Display for SelectTimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "timed out waiting on select".fmt(f)
+    }
+}
+
+impl Error for SelectTimeoutError {}
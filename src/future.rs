@@ -0,0 +1,198 @@
+use std::{
+    future::Future,
+    pin::{pin, Pin},
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
+
+use crate::{OverflowPolicy, Reciever, RecvError, SendError, Sender};
+
+impl<T> Sender<T> {
+    // Async version of send(). Instead of blocking the whole executor thread on a full (or rendezvous) channel, the task is woken up once the reciever makes room. Dropping the future halfway through a rendezvous handoff leaves the message in the channel
+    pub fn send_async(&self, data: T) -> SendFuture<'_, T> {
+        SendFuture {
+            sender: self,
+            data: Some(data),
+            ticket: None,
+        }
+    }
+
+    // Async version of closed(). Lets a producer select on the channel closing next to whatever it is working on
+    pub fn closed_async(&self) -> ClosedFuture<'_, T> {
+        ClosedFuture { sender: self }
+    }
+}
+
+pub struct SendFuture<'a, T> {
+    sender: &'a Sender<T>,
+    data: Option<T>,
+    ticket: Option<u64>, // Set once a rendezvous message has been queued and we are waiting for the reciever to take it
+}
+
+// We never hand out a pinned reference to `data`, so the future can be moved around freely even if T itself is not Unpin
+impl<T> Unpin for SendFuture<'_, T> {}
+
+impl<T> Future for SendFuture<'_, T> {
+    type Output = Result<(), SendError<T>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let shared = &this.sender.shared;
+        let mut channel = shared.lock();
+
+        if let Some(ticket) = this.ticket {
+            if channel.received >= ticket {
+                return Poll::Ready(Ok(()));
+            }
+            if shared.is_poisoned() {
+                return Poll::Ready(Err(SendError::Poisoned(channel.take_back_handoff())));
+            }
+            if !channel.is_channel_still_active {
+                return Poll::Ready(Err(SendError::Disconnected(channel.take_back_handoff())));
+            }
+            register(&mut channel.send_wakers, cx.waker());
+            return Poll::Pending;
+        }
+
+        let mut data = this
+            .data
+            .take()
+            .expect("SendFuture polled after completion");
+        if shared.is_poisoned() {
+            return Poll::Ready(Err(SendError::Poisoned(data)));
+        }
+        if !channel.is_channel_still_active {
+            return Poll::Ready(Err(SendError::Disconnected(data)));
+        }
+        let mut evicted = Vec::new();
+        if channel.is_full() {
+            if shared.overflow_policy == OverflowPolicy::Block {
+                this.data = Some(data);
+                register(&mut channel.send_wakers, cx.waker());
+                return Poll::Pending;
+            }
+            match shared.overflow(&mut channel, data, &mut evicted)? {
+                Some(kept) => data = kept,
+                None => {
+                    drop(channel);
+                    shared.evict(evicted);
+                    return Poll::Ready(Ok(()));
+                }
+            }
+        }
+
+        let ticket = shared.push(&mut channel, data);
+        if channel.capacity == Some(0) {
+            this.ticket = Some(ticket);
+            register(&mut channel.send_wakers, cx.waker());
+            shared.notify_available(channel);
+            return Poll::Pending;
+        }
+        shared.notify_available(channel);
+        shared.evict(evicted);
+        Poll::Ready(Ok(()))
+    }
+}
+
+pub struct ClosedFuture<'a, T> {
+    sender: &'a Sender<T>,
+}
+
+impl<T> Future for ClosedFuture<'_, T> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut channel = self.sender.shared.lock();
+        if !channel.is_channel_still_active {
+            return Poll::Ready(());
+        }
+        register(&mut channel.closed_wakers, cx.waker());
+        Poll::Pending
+    }
+}
+
+impl<T> Reciever<T> {
+    // Non-blocking building block for async consumers, shaped like Stream::poll_next: Ready(None) means every sender is gone. When nothing is available the task's waker is registered next to the `available` condvar, so it gets woken up by the same senders that would wake a blocked thread
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        // A poisoned channel ends the stream just like a disconnected one
+        self.poll_recv_result(cx).map(Result::ok)
+    }
+
+    // poll_recv() that tells why there's nothing more to receive
+    fn poll_recv_result(&mut self, cx: &mut Context<'_>) -> Poll<Result<T, RecvError>> {
+        if let Some(val) = self.pop_buffer() {
+            return Poll::Ready(Ok(val));
+        }
+
+        let mut channel = self.shared.lock();
+        if self.shared.is_poisoned() {
+            return Poll::Ready(Err(RecvError::Poisoned));
+        }
+        let (val, freed) = self
+            .shared
+            .pop_into(&mut channel, &mut self.buffer, &mut self.claimed);
+        let disconnected = channel.is_disconnected();
+        if val.is_none() && !disconnected {
+            register(&mut channel.recv_wakers, cx.waker());
+        }
+        self.shared.after_recv(channel, freed);
+
+        match val {
+            Some(val) => Poll::Ready(Ok(val)),
+            None if disconnected => Poll::Ready(Err(RecvError::Disconnected)),
+            None => Poll::Pending,
+        }
+    }
+
+    // Async version of recv()
+    pub fn recv_async(&mut self) -> RecvFuture<'_, T> {
+        RecvFuture { reciever: self }
+    }
+}
+
+pub struct RecvFuture<'a, T> {
+    reciever: &'a mut Reciever<T>,
+}
+
+impl<T> Future for RecvFuture<'_, T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.reciever.poll_recv_result(cx)
+    }
+}
+
+// An executor usually polls the same task with the same waker over and over, so don't pile up copies of it while the task keeps waiting
+pub(crate) fn register(wakers: &mut Vec<Waker>, waker: &Waker) {
+    if !wakers.iter().any(|registered| registered.will_wake(waker)) {
+        wakers.push(waker.clone());
+    }
+}
+
+pub(crate) struct ThreadWaker(pub(crate) Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+// Tiny executor that drives a single future to completion on the current thread, parking it in between polls. Good enough to use the async API from tests and from plain threads without pulling in a runtime
+pub fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        if let Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+            return output;
+        }
+        // park() may return spuriously, in which case we just poll again
+        thread::park();
+    }
+}
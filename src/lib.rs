@@ -0,0 +1,1019 @@
+use std::{
+    collections::VecDeque,
+    hint,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, PoisonError, Weak,
+    },
+    task::Waker,
+    thread,
+    time::{Duration, Instant},
+};
+
+pub mod broadcast;
+mod buffered;
+mod error;
+mod future;
+pub mod lockfree;
+mod metrics;
+#[cfg(test)]
+mod model;
+pub mod oneshot;
+pub mod priority;
+mod rate_limited;
+mod select;
+mod sync;
+#[cfg(test)]
+mod tests;
+pub mod watch;
+
+pub use buffered::BufferedSender;
+pub use error::{
+    RateLimitError, RecvError, RecvTimeoutError, SelectTimeoutError, SendError, TryRecvError,
+    TrySelectError,
+};
+pub use future::{block_on, ClosedFuture, RecvFuture, SendFuture};
+pub use metrics::Stats;
+pub use rate_limited::{RateLimitMode, RateLimitedSender};
+pub use select::Select;
+
+use metrics::Metrics;
+use sync::{Condvar, Mutex, MutexGuard};
+
+// Sender
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+// We need manual implementation of Clone for Sender, because the derive[Clone] generates a Clone impl in which it adds a Clone trait bound to T which is not what we want. We just want to clone the inner Arc value and not the T. Hence we need manual Clone impl
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        let mut inner = self.shared.lock();
+        inner.senders_count += 1;
+
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut inner = self.shared.lock();
+        inner.senders_count -= 1;
+        if inner.senders_count == 0 {
+            // If all senders goes out of scope, we need to tell every receiver to wake up
+            let wakers = std::mem::take(&mut inner.recv_wakers);
+            self.shared.bump_version();
+            drop(inner);
+            self.shared.available.notify_all();
+            wakers.into_iter().for_each(Waker::wake);
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    pub fn send(&self, mut data: T) -> Result<(), SendError<T>> {
+        let mut channel = self.shared.lock();
+        let mut evicted = Vec::new();
+
+        // For a bounded channel, park until the receiver frees up a slot (unless the OverflowPolicy says otherwise). This is a loop for the same reason as the wait in recv(), and because another sender may grab the slot before we get the lock back
+        loop {
+            if self.shared.is_poisoned() {
+                return Err(SendError::Poisoned(data));
+            }
+            if !channel.is_channel_still_active {
+                return Err(SendError::Disconnected(data));
+            };
+
+            if !channel.is_full() {
+                break;
+            }
+            if self.shared.overflow_policy == OverflowPolicy::Block {
+                channel = self.shared.wait(&self.shared.space_available, channel);
+                continue;
+            }
+
+            match self.shared.overflow(&mut channel, data, &mut evicted)? {
+                Some(kept) => {
+                    data = kept;
+                    break;
+                }
+                None => {
+                    drop(channel);
+                    self.shared.evict(evicted);
+                    return Ok(());
+                }
+            }
+        }
+
+        let ticket = self.shared.push(&mut channel, data);
+        let rendezvous = channel.capacity == Some(0);
+        self.shared.notify_available(channel);
+        self.shared.evict(evicted);
+
+        if rendezvous {
+            return self.wait_for_handoff(ticket);
+        }
+        Ok(())
+    }
+
+    // Sends every item of `items` under one lock and with one notify, instead of paying for both on every message. A bounded channel takes what fits, wakes up the reciever and waits for room like send() does. A rendezvous channel has to hand over every message on its own, so there this is just send() in a loop. If the reciever goes away halfway through, the error holds whatever hasn't been sent yet
+    pub fn send_all<I>(&self, items: I) -> Result<(), SendError<Vec<T>>>
+    where
+        I: IntoIterator<Item = T>,
+    {
+        // Run the caller's iterator before taking the lock. Under it, a panic would poison the channel and touching the channel would deadlock
+        let mut items = items.into_iter().collect::<Vec<T>>().into_iter().peekable();
+        let mut channel = self.shared.lock();
+        let mut evicted = Vec::new();
+
+        if channel.capacity == Some(0) {
+            drop(channel);
+            while let Some(data) = items.next() {
+                if let Err(err) = self.send(data) {
+                    return Err(err.map(|data| std::iter::once(data).chain(items).collect()));
+                }
+            }
+            return Ok(());
+        }
+
+        loop {
+            if self.shared.is_poisoned() {
+                return Err(SendError::Poisoned(items.collect()));
+            }
+            if !channel.is_channel_still_active {
+                return Err(SendError::Disconnected(items.collect()));
+            }
+
+            let blocking = self.shared.overflow_policy == OverflowPolicy::Block;
+            let mut pushed = false;
+            while let Some(mut data) = items.next_if(|_| !blocking || !channel.is_full()) {
+                if channel.is_full() {
+                    match self.shared.overflow(&mut channel, data, &mut evicted) {
+                        Ok(Some(kept)) => data = kept,
+                        Ok(None) => continue,
+                        Err(err) => {
+                            drop(channel);
+                            self.shared.evict(evicted);
+                            return Err(
+                                err.map(|data| std::iter::once(data).chain(items).collect())
+                            );
+                        }
+                    }
+                }
+                self.shared.push(&mut channel, data);
+                pushed = true;
+            }
+            let done = items.peek().is_none();
+
+            if pushed {
+                self.shared.notify_available(channel);
+                self.shared.evict(std::mem::take(&mut evicted));
+                if done {
+                    return Ok(());
+                }
+                channel = self.shared.lock();
+            } else if done {
+                drop(channel);
+                self.shared.evict(evicted);
+                return Ok(());
+            } else {
+                channel = self.shared.wait(&self.shared.space_available, channel);
+            }
+        }
+    }
+
+    // In a rendezvous channel the message is only handed over once the reciever has actually popped it. Since is_full() lets only one message into the queue at a time, our message is the one in the queue until `received` catches up with our ticket
+    fn wait_for_handoff(&self, ticket: u64) -> Result<(), SendError<T>> {
+        let mut channel = self.shared.lock();
+        while channel.received < ticket {
+            if self.shared.is_poisoned() {
+                return Err(SendError::Poisoned(channel.take_back_handoff()));
+            }
+            if !channel.is_channel_still_active {
+                return Err(SendError::Disconnected(channel.take_back_handoff()));
+            }
+            channel = self.shared.wait(&self.shared.space_available, channel);
+        }
+        Ok(())
+    }
+
+    // Closes the channel for every sender at once, without waiting for all of them to be dropped. The reciever still gets whatever was sent before
+    pub fn close_channel(&self) {
+        let inner = self.shared.lock();
+        self.shared.close(inner);
+    }
+
+    // True once the channel has been closed from either end or the reciever is gone, i.e. send() would fail
+    pub fn is_closed(&self) -> bool {
+        let inner = self.shared.lock();
+        !inner.is_channel_still_active
+    }
+
+    // Blocks until is_closed() turns true, so that a producer can stop doing work nobody is going to look at. See closed_async() for the async version
+    pub fn closed(&self) {
+        let mut channel = self.shared.lock();
+        while channel.is_channel_still_active {
+            channel = self
+                .shared
+                .closed
+                .wait(channel)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    // Snapshot of the channel's metrics. None unless the channel was built with Builder::metrics(true)
+    pub fn stats(&self) -> Option<Stats> {
+        self.shared.stats()
+    }
+
+    // How many messages OverflowPolicy::DropNewest or DropOldest have thrown away so far. Messages rejected under OverflowPolicy::Fail aren't counted, the sender got them back
+    pub fn discarded(&self) -> u64 {
+        self.shared.lock().discarded
+    }
+
+    // A handle that can be turned back into a Sender later on, but doesn't count as a sender in the meantime. So once every (strong) Sender is gone the reciever sees the channel as disconnected, no matter how many WeakSenders are lying around
+    pub fn downgrade(&self) -> WeakSender<T> {
+        WeakSender {
+            shared: Arc::downgrade(&self.shared),
+        }
+    }
+}
+
+pub struct WeakSender<T> {
+    shared: Weak<Shared<T>>,
+}
+
+// Same reason as for Sender, derive would want T: Clone
+impl<T> Clone for WeakSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: Weak::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> WeakSender<T> {
+    // Only works while at least one Sender is still around. Once the last one is gone the recievers may already have been told that the channel is disconnected, so there's no coming back from that
+    pub fn upgrade(&self) -> Option<Sender<T>> {
+        let shared = self.shared.upgrade()?;
+        let mut inner = shared.lock();
+        if inner.senders_count == 0 {
+            return None;
+        }
+        inner.senders_count += 1;
+        drop(inner);
+
+        Some(Sender { shared })
+    }
+}
+
+// Reciever
+pub struct Reciever<T> {
+    shared: Arc<Shared<T>>,
+    // Instead of attaining lock on each recv() call, maintain a buffer that stores the current elements in the queue by popping everything from queue during first recv() call. Next time, return it from the buffer instead of attaining lock. If the reciever has been cloned, each one only takes its share of the queue (see Inner::pop_into)
+    buffer: VecDeque<T>,
+    claimed: usize, // How many of the `buffered` slots in Inner belong to this reciever's buffer
+}
+
+// Cloning a reciever turns the channel into a multi-consumer one: every message is still delivered exactly once, to whichever reciever gets to it first
+impl<T> Clone for Reciever<T> {
+    fn clone(&self) -> Self {
+        let mut inner = self.shared.lock();
+        inner.receivers_count += 1;
+
+        Self {
+            shared: Arc::clone(&self.shared),
+            buffer: VecDeque::new(),
+            claimed: 0,
+        }
+    }
+}
+
+impl<T> Drop for Reciever<T> {
+    fn drop(&mut self) {
+        if let Some(metrics) = &self.shared.metrics {
+            metrics.on_buffer_released(self.buffer.len());
+        }
+        let mut inner = self.shared.lock();
+        inner.receivers_count -= 1;
+
+        if inner.receivers_count > 0 {
+            // Other recievers are still around, so put whatever we had buffered back at the front of the queue for them instead of dropping it. The slots of what we already consumed go back to the senders
+            let freed = self.claimed - self.buffer.len();
+            inner.buffered -= self.claimed;
+            while let Some(val) = self.buffer.pop_back() {
+                inner.queue.push_front(val);
+            }
+            self.shared.after_recv(inner, freed);
+            return;
+        }
+
+        // Nobody is left to receive what's still in the channel. If there's a dead letter hook, everything goes there instead of being dropped along with the channel. Except for a rendezvous message, its sender takes it back
+        let mut dead = Vec::new();
+        if self.shared.dead_letter.is_some() {
+            dead.extend(self.buffer.drain(..));
+            if inner.capacity != Some(0) {
+                dead.extend(inner.queue.drain(..));
+            }
+        }
+        self.shared.close(inner);
+
+        // Only now that the lock is released, the hook may well send the messages somewhere else
+        if let Some(dead_letter) = &self.shared.dead_letter {
+            dead.into_iter().for_each(dead_letter);
+        }
+    }
+}
+
+impl<T> Iterator for Reciever<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().ok()
+    }
+}
+
+impl<T> Reciever<T> {
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        if let Some(val) = self.pop_buffer() {
+            return Ok(val);
+        }
+
+        // Without a deadline recv_until() can't time out
+        self.recv_until(None).map_err(|err| match err {
+            RecvTimeoutError::Poisoned => RecvError::Poisoned,
+            _ => RecvError::Disconnected,
+        })
+    }
+
+    // Like recv() but gives up once `timeout` has passed, so that the caller can wake up periodically even if nothing arrives
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        // A timeout too big to be represented as an Instant is as good as waiting forever
+        let deadline = Instant::now().checked_add(timeout);
+        self.recv_until(deadline)
+    }
+
+    pub fn recv_deadline(&mut self, deadline: Instant) -> Result<T, RecvTimeoutError> {
+        self.recv_until(Some(deadline))
+    }
+
+    fn recv_until(&mut self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        if let Some(val) = self.pop_buffer() {
+            return Ok(val);
+        }
+
+        let mut channel = self.shared.lock();
+        loop {
+            if self.shared.is_poisoned() {
+                return Err(RecvTimeoutError::Poisoned);
+            }
+            match self
+                .shared
+                .pop_into(&mut channel, &mut self.buffer, &mut self.claimed)
+            {
+                (Some(val), freed) => {
+                    self.shared.after_recv(channel, freed);
+                    return Ok(val);
+                }
+                // If all senders went out of scope (or the channel was closed), whoever did it would have called notify on receiver which then wakes up and comes to this match below
+                (None, _) if channel.is_disconnected() => {
+                    return Err(RecvTimeoutError::Disconnected)
+                }
+                (None, freed) if freed > 0 && channel.capacity.is_some() => {
+                    // Wake up the senders before going to sleep, otherwise a full channel whose buffer we just drained would never be refilled. Then come back for the lock since one of them may have sent something meanwhile
+                    self.shared.after_recv(channel, freed);
+                    channel = self.shared.lock();
+                }
+                (None, _) => {
+                    // This section has to be in loop, because OS  will ensure that this thread only wakes up when other thread notifies this. But it can happen that this thread was notified due to some other reason. In that case we still don't have data, so loop happens and again this thread goes to slepp
+                    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                    channel = self.shared.wait_available(channel, deadline);
+                }
+            }
+        }
+    }
+
+    // Same as recv() but never goes to sleep. Lets the caller tell "nothing yet" apart from "all senders are gone"
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        if let Some(val) = self.pop_buffer() {
+            return Ok(val);
+        }
+
+        let mut channel = self.shared.lock();
+        if self.shared.is_poisoned() {
+            return Err(TryRecvError::Poisoned);
+        }
+        let (val, freed) = self
+            .shared
+            .pop_into(&mut channel, &mut self.buffer, &mut self.claimed);
+        let disconnected = channel.is_disconnected();
+        self.shared.after_recv(channel, freed);
+
+        match val {
+            Some(val) => Ok(val),
+            None if disconnected => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    // Appends up to `max` messages to `out` and returns how many. Blocks like recv() while nothing is available, but once it has something it only takes what's already there: first our buffer, then (once) whatever the queue holds. Fails only if nothing could be received because all senders are gone
+    pub fn recv_many(&mut self, out: &mut Vec<T>, max: usize) -> Result<usize, RecvError> {
+        let start = out.len();
+        let mut fetched = false;
+        while out.len() - start < max {
+            if let Some(val) = self.pop_buffer() {
+                out.push(val);
+                continue;
+            }
+            // Going back to the queue more than once would just be recv() in a loop
+            if fetched {
+                break;
+            }
+            fetched = true;
+
+            let val = match out.len() == start {
+                true => self.recv()?,
+                false => match self.try_recv() {
+                    Ok(val) => val,
+                    Err(_) => break,
+                },
+            };
+            out.push(val);
+        }
+        Ok(out.len() - start)
+    }
+
+    // recv_many() without a limit
+    pub fn drain_available(&mut self) -> Result<Vec<T>, RecvError> {
+        let mut out = Vec::new();
+        self.recv_many(&mut out, usize::MAX)?;
+        Ok(out)
+    }
+
+    // Iterator over whatever is available right now (including the cached buffer). Stops as soon as the channel is empty instead of blocking
+    pub fn try_iter(&mut self) -> TryIter<'_, T> {
+        TryIter { reciever: self }
+    }
+
+    // Stops any further sends (they get their message back in the SendError) without dropping the reciever, so that whatever is already queued or buffered can still be drained. recv() fails once that's done. Closes the channel for every clone of the reciever
+    pub fn close(&self) {
+        let inner = self.shared.lock();
+        self.shared.close(inner);
+    }
+
+    // close() and hand back everything that was sent but not received yet, i.e. our buffer and the whole queue, so that none of it gets lost. Other recievers only keep what's already in their own buffers
+    pub fn close_and_drain(&mut self) -> Vec<T> {
+        if let Some(metrics) = &self.shared.metrics {
+            metrics.on_buffer_released(self.buffer.len());
+        }
+        let mut inner = self.shared.lock();
+        let mut drained: Vec<T> = self.buffer.drain(..).collect();
+        inner.buffered -= std::mem::take(&mut self.claimed);
+
+        // What we take from the queue counts as received, so a rendezvous sender waiting for its handoff is done
+        let queued = inner.queue.len();
+        inner.received += queued as u64;
+        drained.extend(inner.queue.drain(..));
+
+        self.shared.close(inner);
+        drained
+    }
+
+    // True once the channel has been closed from either end or every sender is gone. There may still be messages left to drain
+    pub fn is_closed(&self) -> bool {
+        let inner = self.shared.lock();
+        inner.is_disconnected()
+    }
+
+    // Snapshot of the channel's metrics. None unless the channel was built with Builder::metrics(true)
+    pub fn stats(&self) -> Option<Stats> {
+        self.shared.stats()
+    }
+
+    // See Sender::discarded()
+    pub fn discarded(&self) -> u64 {
+        self.shared.lock().discarded
+    }
+
+    // Serves the next message from our buffer, if there is one. No lock needed
+    fn pop_buffer(&mut self) -> Option<T> {
+        let val = self.buffer.pop_front()?;
+        if let Some(metrics) = &self.shared.metrics {
+            metrics.on_cache_hit();
+        }
+        Some(val)
+    }
+}
+
+pub struct TryIter<'a, T> {
+    reciever: &'a mut Reciever<T>,
+}
+
+impl<T> Iterator for TryIter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.reciever.try_recv().ok()
+    }
+}
+
+// Channel
+struct Shared<T> {
+    inner: Mutex<Inner<T>>,
+    available: Condvar, // This has to be outside of Mutex, because thread1 holding the mutex has to notify thread2 that data is available. if this is inside mutex, then thread2 will indeed be notified but sees that lock is still holded by thread1 and goes to sleep again. The implementation works without this CondVar too. But by using this, the reciever thread doesn't always be executing the loop even though there's no data in queue. This makes sure the receiver thread goes to sleep until the sender thread notifies so that CPU time is not wasted by reciever thread.
+    space_available: Condvar, // Same idea as `available` but in the other direction: senders of a full bounded channel sleep on this one until the reciever frees up a slot
+    closed: Condvar, // For senders waiting in closed(). Separate from `space_available` so that the notify_one() meant for a blocked sender can't end up with them
+    metrics: Option<Metrics>, // Only there if asked for through the Builder
+    dead_letter: Option<DeadLetter<T>>,
+    evicted: Option<DeadLetter<T>>, // Same kind of hook as `dead_letter`, but for the messages thrown away by the OverflowPolicy
+    poison_policy: PoisonPolicy,
+    overflow_policy: OverflowPolicy, // Always Block for unbounded and rendezvous channels
+    wait_strategy: WaitStrategy,
+    version: AtomicU64, // Bumped whenever `available` is notified, lets a spinning reciever notice new messages without taking the lock
+}
+
+type DeadLetter<T> = Box<dyn Fn(T) + Send + Sync>;
+
+impl<T> Drop for Shared<T> {
+    // Normally the last reciever has already handed everything to the dead letter hook. What can still be here is a rendezvous message whose SendFuture was dropped halfway through the handoff
+    fn drop(&mut self) {
+        if let Some(dead_letter) = &self.dead_letter {
+            let inner = self.inner.get_mut().unwrap_or_else(PoisonError::into_inner);
+            inner.queue.drain(..).for_each(dead_letter);
+        }
+    }
+}
+
+impl<T> Shared<T> {
+    // Takes the channel lock, even if some thread panicked while holding it. None of our own code can panic halfway through updating Inner, so its state is still good to use. Whether the channel should still be used is up to the PoisonPolicy, see is_poisoned()
+    fn lock(&self) -> MutexGuard<'_, Inner<T>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // True if send and recv should fail with a Poisoned error. Once poisoned, a mutex stays poisoned, so a PoisonPolicy::Fail channel is done for good
+    fn is_poisoned(&self) -> bool {
+        self.poison_policy == PoisonPolicy::Fail && self.inner.is_poisoned()
+    }
+
+    // Inner::push plus the bookkeeping for the metrics
+    fn push(&self, channel: &mut Inner<T>, data: T) -> u64 {
+        let ticket = channel.push(data);
+        if let Some(metrics) = &self.metrics {
+            metrics.on_send(channel.queue.len());
+        }
+        ticket
+    }
+
+    // Inner::pop_into plus the bookkeeping for the metrics
+    fn pop_into(
+        &self,
+        channel: &mut Inner<T>,
+        buffer: &mut VecDeque<T>,
+        claimed: &mut usize,
+    ) -> (Option<T>, usize) {
+        let (val, freed) = channel.pop_into(buffer, claimed);
+        if let (Some(metrics), Some(_)) = (&self.metrics, &val) {
+            metrics.on_cache_miss(buffer.len());
+        }
+        (val, freed)
+    }
+
+    // Called instead of waiting when a sender finds the channel full and the OverflowPolicy isn't Block. Returns the message if it should still be pushed, or None if it was thrown away. Whatever gets thrown away ends up in `evicted`, to be handed to evict() once the lock is released
+    fn overflow(
+        &self,
+        channel: &mut Inner<T>,
+        data: T,
+        evicted: &mut Vec<T>,
+    ) -> Result<Option<T>, SendError<T>> {
+        if self.overflow_policy == OverflowPolicy::Fail {
+            return Err(SendError::Full(data));
+        }
+        channel.discarded += 1;
+        match self.overflow_policy {
+            // If everything in flight has already been moved into a reciever's buffer, the new message is the oldest one we can still get at
+            OverflowPolicy::DropOldest if !channel.queue.is_empty() => {
+                evicted.extend(channel.queue.pop_front());
+                Ok(Some(data))
+            }
+            _ => {
+                evicted.push(data);
+                Ok(None)
+            }
+        }
+    }
+
+    // Hands what overflow() threw away to the evict hook. Must be called without holding the lock
+    fn evict(&self, evicted: Vec<T>) {
+        if let Some(hook) = &self.evicted {
+            evicted.into_iter().for_each(hook);
+        }
+    }
+
+    // Condvar::wait plus the bookkeeping for the metrics. Only measures the time if somebody is going to look at it
+    fn wait<'a>(
+        &self,
+        condvar: &Condvar,
+        channel: MutexGuard<'a, Inner<T>>,
+    ) -> MutexGuard<'a, Inner<T>> {
+        let started = self.metrics.as_ref().map(|_| Instant::now());
+        let channel = condvar
+            .wait(channel)
+            .unwrap_or_else(PoisonError::into_inner);
+        self.record_wait(started);
+        channel
+    }
+
+    fn wait_timeout<'a>(
+        &self,
+        condvar: &Condvar,
+        channel: MutexGuard<'a, Inner<T>>,
+        timeout: Duration,
+    ) -> MutexGuard<'a, Inner<T>> {
+        let started = self.metrics.as_ref().map(|_| Instant::now());
+        let (channel, _) = condvar
+            .wait_timeout(channel, timeout)
+            .unwrap_or_else(PoisonError::into_inner);
+        self.record_wait(started);
+        channel
+    }
+
+    // Waits until a sender has (probably) made something available, the way the WaitStrategy says. Like Condvar::wait this may return early, the caller has to check the queue again either way
+    fn wait_available<'a>(
+        &'a self,
+        mut channel: MutexGuard<'a, Inner<T>>,
+        deadline: Option<Instant>,
+    ) -> MutexGuard<'a, Inner<T>> {
+        if self.wait_strategy != WaitStrategy::Park {
+            // Whoever notifies `available` bumps the version before letting go of the lock, so anything that happens after we let go of it shows up as a new version
+            let seen = self.version.load(Ordering::Acquire);
+            drop(channel);
+            let changed = || self.version.load(Ordering::Acquire) != seen;
+
+            // Only parking counts as a blocking wait in the metrics, spinning and yielding don't put the thread to sleep
+            let woken = match self.wait_strategy {
+                WaitStrategy::SpinThenYieldThenPark { spins, yields } => {
+                    (0..spins).any(|_| {
+                        hint::spin_loop();
+                        changed()
+                    }) || (0..yields).any(|_| {
+                        thread::yield_now();
+                        changed()
+                    })
+                }
+                // BusySpin, Park never gets here
+                _ => loop {
+                    if changed() || deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        break true;
+                    }
+                    hint::spin_loop();
+                },
+            };
+
+            channel = self.lock();
+            // The version may have changed between our last look and getting the lock back, in which case the notify is already gone and parking would miss it
+            if woken || changed() {
+                return channel;
+            }
+        }
+        self.park(channel, deadline)
+    }
+
+    fn park<'a>(
+        &self,
+        channel: MutexGuard<'a, Inner<T>>,
+        deadline: Option<Instant>,
+    ) -> MutexGuard<'a, Inner<T>> {
+        match deadline {
+            // wait method takes in the mutex guard so that this thread is not holding the lock anymore and the sender thread can hold lock and notify this thread once it has sent data
+            None => self.wait(&self.available, channel),
+            // After a spurious wakeup we must only sleep for whatever is left until the deadline, not for the whole timeout again
+            Some(deadline) => self.wait_timeout(
+                &self.available,
+                channel,
+                deadline.saturating_duration_since(Instant::now()),
+            ),
+        }
+    }
+
+    // Must be called with the lock held, see wait_available()
+    fn bump_version(&self) {
+        self.version.fetch_add(1, Ordering::Release);
+    }
+
+    fn record_wait(&self, started: Option<Instant>) {
+        if let (Some(metrics), Some(started)) = (&self.metrics, started) {
+            metrics.on_wait(started.elapsed());
+        }
+    }
+
+    fn stats(&self) -> Option<Stats> {
+        let metrics = self.metrics.as_ref()?;
+        let channel = self.lock();
+        Some(metrics.snapshot(channel.queue.len(), channel.discarded))
+    }
+
+    // Stops any further sends and wakes everybody up: blocked senders so that they hand their message back, and recievers so that they notice once the queue runs dry
+    fn close(&self, mut channel: MutexGuard<'_, Inner<T>>) {
+        channel.is_channel_still_active = false;
+        let mut wakers = std::mem::take(&mut channel.recv_wakers);
+        wakers.append(&mut channel.send_wakers);
+        wakers.append(&mut channel.closed_wakers);
+        self.bump_version();
+        drop(channel);
+
+        self.available.notify_all();
+        self.space_available.notify_all();
+        self.closed.notify_all();
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    // Wakes up the reciever, whether it's a thread sleeping on `available` or a task that registered a waker. Takes the guard so that it is dropped before notifying and the woken up reciever can immediatly take the lock
+    fn notify_available(&self, mut channel: MutexGuard<'_, Inner<T>>) {
+        let wakers = std::mem::take(&mut channel.recv_wakers);
+        self.bump_version();
+        drop(channel);
+        self.available.notify_one();
+        wakers.into_iter().for_each(Waker::wake);
+    }
+
+    // Called by a reciever once it's done with the lock. Hands `freed` slots back to the senders of a bounded channel (be it threads or tasks), and passes on whatever this reciever left in the queue to the other recievers
+    fn after_recv(&self, mut channel: MutexGuard<'_, Inner<T>>, freed: usize) {
+        // We only took our share of the queue (or are a dropped reciever that put its buffer back). Make sure an idle reciever comes for the rest, since the notify meant for it may have woken us up instead. A single reciever always takes the whole queue, so there's nothing left in that case
+        let leftovers = !channel.queue.is_empty();
+        let recv_wakers = match leftovers {
+            true => {
+                self.bump_version();
+                std::mem::take(&mut channel.recv_wakers)
+            }
+            false => Vec::new(),
+        };
+        // Nobody ever waits for space in an unbounded channel
+        let capacity = channel.capacity.filter(|_| freed > 0);
+        let send_wakers = match capacity {
+            Some(_) => std::mem::take(&mut channel.send_wakers),
+            None => Vec::new(),
+        };
+        drop(channel);
+
+        if leftovers {
+            self.available.notify_one();
+        }
+        match (capacity, freed) {
+            (None, _) => {}
+            // Both the sender waiting for its handoff and the ones waiting for the slot sleep on space_available, so all of them have to be woken up
+            (Some(0), _) => self.space_available.notify_all(),
+            (_, 1) => self.space_available.notify_one(),
+            _ => self.space_available.notify_all(),
+        }
+        recv_wakers
+            .into_iter()
+            .chain(send_wakers)
+            .for_each(Waker::wake);
+    }
+}
+
+struct Inner<T> {
+    queue: VecDeque<T>,
+    senders_count: usize,
+    receivers_count: usize,
+    is_channel_still_active: bool, // Set to false once the last reciever is gone or the channel is closed from either end. Senders fail from then on, while recievers can still drain what's queued
+    capacity: Option<usize>,       // None for an unbounded channel
+    buffered: usize, // Number of messages moved into the recievers' buffers. They still count against the capacity until their reciever comes back for the lock, otherwise the bound would not hold
+    sent: u64,       // Total number of messages pushed into the queue
+    received: u64, // Total number of messages popped from the queue. A rendezvous sender compares this against its ticket to know that its message was taken
+    discarded: u64, // Messages thrown away by the OverflowPolicy
+    recv_wakers: Vec<Waker>, // Async counterpart of `available`. Tasks waiting for data register here and are woken up along with the condvar
+    send_wakers: Vec<Waker>, // Async counterpart of `space_available`
+    closed_wakers: Vec<Waker>, // Async counterpart of `closed`
+}
+
+impl<T> Inner<T> {
+    // Recievers can't expect anything beyond what's queued anymore
+    fn is_disconnected(&self) -> bool {
+        self.senders_count == 0 || !self.is_channel_still_active
+    }
+
+    fn is_full(&self) -> bool {
+        match self.capacity {
+            None => false,
+            // A rendezvous channel is "full" while some other sender's handoff is still in progress
+            Some(0) => !self.queue.is_empty(),
+            Some(capacity) => self.queue.len() + self.buffered >= capacity,
+        }
+    }
+
+    // Returns the ticket of the pushed message, see `received`
+    fn push(&mut self, data: T) -> u64 {
+        self.queue.push_back(data);
+        self.sent += 1;
+        self.sent
+    }
+
+    // The reciever went away before taking a rendezvous message. Since is_full() lets only one message into the queue at a time it is still sitting there, so hand it back to the sender instead of letting it die in the queue
+    fn take_back_handoff(&mut self) -> T {
+        self.queue
+            .pop_back()
+            .expect("undelivered rendezvous message must still be queued")
+    }
+
+    // Must only be called with a reciever's (now empty) buffer and its claim on `buffered`. Pops the next message, moves this reciever's share of the rest of the queue into the buffer and returns how many slots were given back to the senders along the way
+    fn pop_into(&mut self, buffer: &mut VecDeque<T>, claimed: &mut usize) -> (Option<T>, usize) {
+        // Since the buffer is empty, everything we moved into it last time has been consumed and its slots can be handed back to the senders
+        let mut freed = std::mem::take(claimed);
+        self.buffered -= freed;
+        let val = self.queue.pop_front();
+        if val.is_some() {
+            freed += 1;
+            self.received += 1;
+            // A single reciever can swap the whole queue in one go. With several of them, only take our share so that one consumer doesn't hoard the whole batch while the others sit idle
+            let batch = self.queue.len() / self.receivers_count;
+            if batch == self.queue.len() {
+                std::mem::swap(&mut self.queue, buffer);
+            } else {
+                buffer.extend(self.queue.drain(..batch));
+            }
+            *claimed = batch;
+            self.buffered += batch;
+        }
+        (val, freed)
+    }
+}
+
+pub fn channel<T>() -> (Sender<T>, Reciever<T>) {
+    Builder::new().build()
+}
+
+// Bounded version of channel(). Once `capacity` messages are in flight (queued or sitting in the reciever's buffer), send() blocks until the reciever makes room. A capacity of 0 makes a rendezvous channel where send() only returns once the reciever has taken the message
+pub fn sync_channel<T>(capacity: usize) -> (Sender<T>, Reciever<T>) {
+    Builder::new().capacity(capacity).build()
+}
+
+// For channels that need more than channel() and sync_channel() offer. Starts out as an unbounded channel without metrics
+//
+// let (tx, rx) = Builder::<Job>::new().capacity(1024).metrics(true).build();
+pub struct Builder<T> {
+    capacity: Option<usize>,
+    metrics: bool,
+    dead_letter: Option<DeadLetter<T>>,
+    evicted: Option<DeadLetter<T>>,
+    poison_policy: PoisonPolicy,
+    overflow_policy: OverflowPolicy,
+    wait_strategy: WaitStrategy,
+}
+
+// What to do once a thread has panicked while holding the channel's lock. Drop never panics either way
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PoisonPolicy {
+    // Carry on as if nothing happened. The channel's state is never left half-updated, so this is safe
+    #[default]
+    Recover,
+    // Fail every send and recv from then on with a Poisoned error. Messages already in a reciever's buffer are still handed out
+    Fail,
+}
+
+// What send() does when a bounded channel is full. Everything but Block keeps the producers from ever waiting on a slow reciever, at the cost of losing (or, with Fail, handing back) messages. Every message lost this way is counted, see Sender::discarded()
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    // Wait for the reciever to make room
+    #[default]
+    Block,
+    // Fail right away with SendError::Full, handing the message back
+    Fail,
+    // Throw away the message being sent and pretend it was sent
+    DropNewest,
+    // Throw away the oldest message still in the queue to make room for the new one
+    DropOldest,
+}
+
+// How a reciever waits once the queue is empty. Parking costs nothing while waiting, but waking up again goes through the OS and takes a while. The other two trade CPU time for latency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WaitStrategy {
+    // Go to sleep on the condvar right away
+    #[default]
+    Park,
+    // Spin `spins` times, then yield the thread `yields` times, looking for new messages in between, and only then go to sleep. Good when messages usually arrive within microseconds of each other
+    SpinThenYieldThenPark {
+        spins: u32,
+        yields: u32,
+    },
+    // Never sleep, keep a core busy until a message arrives. Only makes sense with a core to spare for every waiting reciever
+    BusySpin,
+}
+
+// Not derived, that would require T: Default
+impl<T> Default for Builder<T> {
+    fn default() -> Self {
+        Builder {
+            capacity: None,
+            metrics: false,
+            dead_letter: None,
+            evicted: None,
+            poison_policy: PoisonPolicy::Recover,
+            overflow_policy: OverflowPolicy::Block,
+            wait_strategy: WaitStrategy::Park,
+        }
+    }
+}
+
+impl<T> Builder<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Makes the channel bounded, same as sync_channel(capacity)
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    // Keeps track of the numbers returned by stats() on either end. Off by default since it costs a few atomic operations per message
+    pub fn metrics(mut self, enabled: bool) -> Self {
+        self.metrics = enabled;
+        self
+    }
+
+    // Called with every message that would otherwise be dropped without ever being received, i.e. whatever is left in the queue and in the reciever's buffer when the last reciever goes away. Runs on the thread dropping the reciever, after the channel has been closed and without holding its lock, so it's fine for it to send the messages on to another channel
+    pub fn dead_letter<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        self.dead_letter = Some(Box::new(hook));
+        self
+    }
+
+    pub fn poison_policy(mut self, policy: PoisonPolicy) -> Self {
+        self.poison_policy = policy;
+        self
+    }
+
+    // Only applies to bounded channels with a capacity of at least 1. An unbounded channel is never full, and a rendezvous channel has nothing it could throw away, so both always block
+    pub fn overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+
+    // Called with every message thrown away by OverflowPolicy::DropNewest or DropOldest. Runs on the sending thread, after the lock has been released
+    pub fn on_evict<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        self.evicted = Some(Box::new(hook));
+        self
+    }
+
+    // Only affects recievers waiting in recv(), recv_timeout() and friends. Senders waiting for room in a bounded channel always park
+    pub fn wait_strategy(mut self, strategy: WaitStrategy) -> Self {
+        self.wait_strategy = strategy;
+        self
+    }
+
+    pub fn build(self) -> (Sender<T>, Reciever<T>) {
+        let inner = Inner {
+            queue: VecDeque::new(),
+            senders_count: 1,
+            receivers_count: 1,
+            is_channel_still_active: true,
+            capacity: self.capacity,
+            buffered: 0,
+            sent: 0,
+            received: 0,
+            discarded: 0,
+            recv_wakers: Vec::new(),
+            send_wakers: Vec::new(),
+            closed_wakers: Vec::new(),
+        };
+
+        let shared = Arc::new(Shared {
+            inner: Mutex::new(inner),
+            available: Condvar::new(),
+            space_available: Condvar::new(),
+            closed: Condvar::new(),
+            metrics: self.metrics.then(Metrics::default),
+            dead_letter: self.dead_letter,
+            evicted: self.evicted,
+            poison_policy: self.poison_policy,
+            overflow_policy: match self.capacity {
+                None | Some(0) => OverflowPolicy::Block,
+                Some(_) => self.overflow_policy,
+            },
+            wait_strategy: self.wait_strategy,
+            version: AtomicU64::new(0),
+        });
+
+        let sender = Sender {
+            shared: shared.clone(),
+        };
+
+        let receiver = Reciever {
+            shared: shared.clone(),
+            buffer: VecDeque::new(),
+            claimed: 0,
+        };
+
+        (sender, receiver)
+    }
+}
@@ -0,0 +1,346 @@
+// Lock-free flavour of the channel. Same Sender/Reciever API as the mutex based one, but the queue is a linked list of fixed size blocks where senders claim a slot with a single CAS on the tail index, so they never take a lock. The only lock left is the one the reciever parks on when the queue is empty, and senders only touch it if the reciever is actually asleep
+use std::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    ptr,
+    sync::{
+        atomic::{self, AtomicBool, AtomicPtr, AtomicU8, AtomicUsize, Ordering},
+        Arc, Condvar, Mutex, MutexGuard, PoisonError,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{RecvError, RecvTimeoutError, SendError, TryRecvError};
+
+// Every block spans LAP indices. The last index of a lap doesn't have a slot, a tail sitting on it means the block is full and the sender that took the last slot is busy installing the next block
+const LAP: usize = 32;
+const BLOCK_CAP: usize = LAP - 1;
+
+// Sender
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.senders_count.fetch_add(1, Ordering::Relaxed);
+
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if self.shared.senders_count.fetch_sub(1, Ordering::SeqCst) == 1 {
+            // Unlike send() we always go through the lock here. The reciever checks senders_count while holding it, so it either sees the count drop to zero or is already waiting when we notify
+            let _guard = self.shared.lock();
+            self.shared.available.notify_one();
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    pub fn send(&self, data: T) -> Result<(), SendError<T>> {
+        if !self.shared.is_channel_still_active.load(Ordering::Acquire) {
+            return Err(SendError::Disconnected(data));
+        }
+
+        self.shared.queue.push(data);
+
+        // Pairs with the SeqCst store of receiver_waiting in Reciever::park(). Either the reciever sees our message when it checks the queue again, or we see that it is going to sleep
+        atomic::fence(Ordering::SeqCst);
+        if self.shared.receiver_waiting.load(Ordering::SeqCst) {
+            // The reciever holds the lock from announcing that it is about to sleep until it actually sleeps, so taking it here makes sure the notify isn't lost
+            let _guard = self.shared.lock();
+            self.shared.available.notify_one();
+        }
+        Ok(())
+    }
+}
+
+// Reciever
+pub struct Reciever<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Drop for Reciever<T> {
+    fn drop(&mut self) {
+        self.shared
+            .is_channel_still_active
+            .store(false, Ordering::Release);
+    }
+}
+
+impl<T> Iterator for Reciever<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().ok()
+    }
+}
+
+impl<T> Reciever<T> {
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        self.recv_until(None).map_err(|_| RecvError::Disconnected)
+    }
+
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = Instant::now().checked_add(timeout);
+        self.recv_until(deadline)
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        // SAFETY: `&mut self` on the one and only Reciever makes us the only consumer
+        if let Some(val) = unsafe { self.shared.queue.pop() } {
+            return Ok(val);
+        }
+        if self.shared.senders_count.load(Ordering::SeqCst) == 0 {
+            // The last messages may have been pushed just before their sender went away
+            // SAFETY: as above, we are the only consumer
+            return unsafe { self.shared.queue.pop() }.ok_or(TryRecvError::Disconnected);
+        }
+        Err(TryRecvError::Empty)
+    }
+
+    fn recv_until(&mut self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        loop {
+            match self.try_recv() {
+                Ok(val) => return Ok(val),
+                Err(TryRecvError::Empty) => {}
+                // No lock, so nothing to poison either
+                Err(_) => return Err(RecvTimeoutError::Disconnected),
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            self.park(deadline);
+        }
+    }
+
+    // Goes to sleep until a sender pushes something or the last sender goes away. May return early, the caller is expected to check the queue again
+    fn park(&mut self, deadline: Option<Instant>) {
+        let shared = &*self.shared;
+        let guard = shared.lock();
+        shared.receiver_waiting.store(true, Ordering::SeqCst);
+
+        // Check again now that the senders can see that we are about to sleep. Anything pushed before they could see it would otherwise be missed
+        // SAFETY: only the reciever ever reads the head of the queue
+        let empty = unsafe { shared.queue.is_empty() };
+        if empty && shared.senders_count.load(Ordering::SeqCst) > 0 {
+            match deadline {
+                None => drop(
+                    shared
+                        .available
+                        .wait(guard)
+                        .unwrap_or_else(PoisonError::into_inner),
+                ),
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    drop(
+                        shared
+                            .available
+                            .wait_timeout(guard, timeout)
+                            .unwrap_or_else(PoisonError::into_inner),
+                    );
+                }
+            }
+        }
+        shared.receiver_waiting.store(false, Ordering::Relaxed);
+    }
+}
+
+// Channel
+struct Shared<T> {
+    queue: Queue<T>,
+    senders_count: AtomicUsize,
+    is_channel_still_active: AtomicBool,
+    receiver_waiting: AtomicBool, // Set while the reciever is (about to be) asleep on `available`, so that senders only go for the lock when somebody needs waking up
+    lock: Mutex<()>,
+    available: Condvar,
+}
+
+impl<T> Shared<T> {
+    // The lock guards no data, so there is nothing a panic could have left half-updated
+    fn lock(&self) -> MutexGuard<'_, ()> {
+        self.lock.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+struct Slot<T> {
+    written: AtomicU8, // 0 until the sender that claimed the slot has written its value
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+struct Block<T> {
+    next: AtomicPtr<Block<T>>,
+    slots: [Slot<T>; BLOCK_CAP],
+}
+
+impl<T> Block<T> {
+    fn new() -> Box<Self> {
+        Box::new(Block {
+            next: AtomicPtr::new(ptr::null_mut()),
+            slots: std::array::from_fn(|_| Slot {
+                written: AtomicU8::new(0),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            }),
+        })
+    }
+
+    // The sender that takes the last slot of a block links the next one before writing its value, so this only ever spins for a moment
+    fn wait_next(&self) -> *mut Block<T> {
+        loop {
+            let next = self.next.load(Ordering::Acquire);
+            if !next.is_null() {
+                return next;
+            }
+            std::hint::spin_loop();
+        }
+    }
+}
+
+// Position of the consumer. Only ever touched by the reciever (or by Drop), hence no atomics
+struct Head<T> {
+    index: usize,
+    block: *mut Block<T>,
+}
+
+// Blocks are only ever freed by the consumer, once its head has moved past the last slot of a block. What makes that sound:
+// - A claimed slot keeps its block alive. The consumer can only get past a slot by reading it, and a slot is only read once its sender has written the value. Storing `written` is the last thing a sender does with the block
+// - The sender that claims the last slot (index BLOCK_CAP - 1) links `next` before writing its value. So once that slot is read, `next` is set and nobody is going to touch the old block anymore
+// - A sender only dereferences the block it loaded from `tail_block` after its CAS on `tail_index` succeeded. Since the index is monotonic, a successful CAS means the block is still the tail block, and the claimed slot keeps it alive from then on
+struct Queue<T> {
+    tail_index: AtomicUsize, // Monotonic, so a successful CAS on it also tells a sender that `tail_block` is still the block it loaded
+    tail_block: AtomicPtr<Block<T>>,
+    head: UnsafeCell<Head<T>>,
+}
+
+// SAFETY: values are moved from the senders to the reciever, and the head is only accessed by the single reciever
+unsafe impl<T: Send> Send for Queue<T> {}
+unsafe impl<T: Send> Sync for Queue<T> {}
+
+impl<T> Queue<T> {
+    fn new() -> Self {
+        let block = Box::into_raw(Block::new());
+        Queue {
+            tail_index: AtomicUsize::new(0),
+            tail_block: AtomicPtr::new(block),
+            head: UnsafeCell::new(Head { index: 0, block }),
+        }
+    }
+
+    fn push(&self, data: T) {
+        let mut next_block = None;
+        let mut tail = self.tail_index.load(Ordering::Acquire);
+        loop {
+            let offset = tail % LAP;
+            if offset == BLOCK_CAP {
+                // Another sender is installing the next block, wait for it to finish
+                thread::yield_now();
+                tail = self.tail_index.load(Ordering::Acquire);
+                continue;
+            }
+            // If we are about to take the last slot, allocate the next block before claiming it so that the others don't wait on an allocation
+            if offset + 1 == BLOCK_CAP && next_block.is_none() {
+                next_block = Some(Block::new());
+            }
+
+            let block = self.tail_block.load(Ordering::Acquire);
+            match self.tail_index.compare_exchange_weak(
+                tail,
+                tail + 1,
+                Ordering::SeqCst,
+                Ordering::Acquire,
+            ) {
+                // SAFETY: we claimed slot `offset` of `block`, which keeps the block alive until the consumer has read the value we are about to write (see Queue). Nobody else writes to this slot
+                Ok(_) => unsafe {
+                    if offset + 1 == BLOCK_CAP {
+                        let next_block = Box::into_raw(next_block.take().unwrap());
+                        self.tail_block.store(next_block, Ordering::Release);
+                        // Skip the slot-less index at the end of the lap
+                        self.tail_index.fetch_add(1, Ordering::Release);
+                        (*block).next.store(next_block, Ordering::Release);
+                    }
+
+                    let slot = &(*block).slots[offset];
+                    slot.value.get().write(MaybeUninit::new(data));
+                    slot.written.store(1, Ordering::Release);
+                    return;
+                },
+                Err(current) => tail = current,
+            }
+        }
+    }
+
+    // Takes the next message off the head of the queue. None if there is nothing to take right now
+    //
+    // # Safety
+    //
+    // Must only be called by the single consumer, i.e. the Reciever or Queue's Drop. The head isn't synchronized at all
+    unsafe fn pop(&self) -> Option<T> {
+        // SAFETY: we are the only consumer, so nothing else holds a reference to the head. `head.block` is never freed by anyone else
+        let head = &mut *self.head.get();
+        loop {
+            let offset = head.index % LAP;
+            if offset == BLOCK_CAP {
+                // SAFETY: every slot of this block has been read, so no sender can still be using it, and the last one linked `next` before writing (see Queue)
+                let next = (*head.block).wait_next();
+                drop(Box::from_raw(head.block));
+                head.block = next;
+                head.index += 1;
+                continue;
+            }
+
+            let slot = &(*head.block).slots[offset];
+            if slot.written.load(Ordering::Acquire) == 0 {
+                if self.tail_index.load(Ordering::SeqCst) == head.index {
+                    return None;
+                }
+                // The slot has been claimed but its sender hasn't written the value yet
+                std::hint::spin_loop();
+                continue;
+            }
+
+            head.index += 1;
+            // SAFETY: `written` says the value is there, and moving the head past the slot makes sure it is read only once
+            return Some(slot.value.get().read().assume_init());
+        }
+    }
+
+    // # Safety
+    //
+    // Must only be called by the single consumer, same as pop()
+    unsafe fn is_empty(&self) -> bool {
+        self.tail_index.load(Ordering::SeqCst) == (*self.head.get()).index
+    }
+}
+
+impl<T> Drop for Queue<T> {
+    fn drop(&mut self) {
+        // SAFETY: `&mut self` means every Sender and the Reciever are gone, so we are the only consumer and no sender is halfway through a push. pop() frees every block it gets past, which leaves the head's block as the only one still allocated. It was made by Box::into_raw() and is freed exactly once, here
+        unsafe { while self.pop().is_some() {} }
+        drop(unsafe { Box::from_raw(self.head.get_mut().block) });
+    }
+}
+
+pub fn channel<T>() -> (Sender<T>, Reciever<T>) {
+    let shared = Arc::new(Shared {
+        queue: Queue::new(),
+        senders_count: AtomicUsize::new(1),
+        is_channel_still_active: AtomicBool::new(true),
+        receiver_waiting: AtomicBool::new(false),
+        lock: Mutex::new(()),
+        available: Condvar::new(),
+    });
+
+    let sender = Sender {
+        shared: shared.clone(),
+    };
+
+    let receiver = Reciever { shared };
+
+    (sender, receiver)
+}
@@ -0,0 +1,89 @@
+use std::{
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+// Counters kept by a channel built with Builder::metrics(true). Everything is a relaxed atomic so that recording never needs the channel lock (the buffer hits happen without it), the snapshot is therefore only approximately consistent while messages are flowing
+#[derive(Default)]
+pub(crate) struct Metrics {
+    sent: AtomicU64,
+    cache_hits: AtomicU64, // Messages served from a reciever's buffer, without the lock
+    cache_misses: AtomicU64, // Messages taken from the queue under the lock
+    buffer_depth: AtomicUsize,
+    high_water_mark: AtomicUsize,
+    blocking_waits: AtomicU64,
+    blocked_nanos: AtomicU64,
+}
+
+impl Metrics {
+    // `queue_depth` is the length of the queue right after the push
+    pub(crate) fn on_send(&self, queue_depth: usize) {
+        self.sent.fetch_add(1, Ordering::Relaxed);
+        self.high_water_mark
+            .fetch_max(queue_depth, Ordering::Relaxed);
+    }
+
+    pub(crate) fn on_cache_hit(&self) {
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+        self.buffer_depth.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    // `buffered` is how many messages were swapped into the reciever's buffer along with the one returned
+    pub(crate) fn on_cache_miss(&self, buffered: usize) {
+        self.cache_misses.fetch_add(1, Ordering::Relaxed);
+        self.buffer_depth.fetch_add(buffered, Ordering::Relaxed);
+    }
+
+    // A reciever gave up its buffer, either back to the queue or by dropping it
+    pub(crate) fn on_buffer_released(&self, len: usize) {
+        self.buffer_depth.fetch_sub(len, Ordering::Relaxed);
+    }
+
+    pub(crate) fn on_wait(&self, blocked: Duration) {
+        self.blocking_waits.fetch_add(1, Ordering::Relaxed);
+        self.blocked_nanos
+            .fetch_add(blocked.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self, queue_depth: usize, discarded: u64) -> Stats {
+        let cache_hits = self.cache_hits.load(Ordering::Relaxed);
+        let cache_misses = self.cache_misses.load(Ordering::Relaxed);
+        Stats {
+            sent: self.sent.load(Ordering::Relaxed),
+            received: cache_hits + cache_misses,
+            queue_depth,
+            buffer_depth: self.buffer_depth.load(Ordering::Relaxed),
+            high_water_mark: self.high_water_mark.load(Ordering::Relaxed),
+            blocking_waits: self.blocking_waits.load(Ordering::Relaxed),
+            blocked_time: Duration::from_nanos(self.blocked_nanos.load(Ordering::Relaxed)),
+            cache_hits,
+            cache_misses,
+            discarded,
+        }
+    }
+}
+
+// Snapshot returned by stats() on either end of the channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stats {
+    pub sent: u64,
+    pub received: u64,
+    pub queue_depth: usize,     // Messages in the shared queue right now
+    pub buffer_depth: usize,    // Messages sitting in the recievers' buffers right now
+    pub high_water_mark: usize, // The longest the queue has ever been
+    pub blocking_waits: u64, // How many times a thread went to sleep waiting to send or receive. Async tasks waiting for a wakeup aren't counted, they don't block anybody, and neither are senders waiting in closed()
+    pub blocked_time: Duration, // Total time spent in those waits
+    pub cache_hits: u64,     // Messages served from a reciever's buffer without taking the lock
+    pub cache_misses: u64,   // Messages that had to be taken from the queue
+    pub discarded: u64,      // Messages lost to the OverflowPolicy, see Sender::discarded()
+}
+
+impl Stats {
+    // Share of the received messages that came out of the buffer, 0 if nothing was received yet
+    pub fn cache_hit_ratio(&self) -> f64 {
+        match self.received {
+            0 => 0.0,
+            received => self.cache_hits as f64 / received as f64,
+        }
+    }
+}
@@ -0,0 +1,548 @@
+// Small model checker for the core channel, in the spirit of loom but std-only. Code running under model() gets real threads, but only one of them runs at a time: every lock, wait and notify on the instrumented Mutex/Condvar below hands control to a scheduler which decides who goes next. Each run records the decisions it made, and the next run replays them up to the last one that still has an untried alternative, so that model() walks through every distinct schedule (depth first). A schedule where nobody can make progress is reported as a deadlock, which is how lost wakeups show up
+//
+// To keep the number of schedules manageable a thread is only switched away from while it could have kept running (a preemption) at most PREEMPTION_BOUND times per run. Most concurrency bugs need very few preemptions to show up. Spurious wakeups are not modelled
+//
+// Outside of model() the instrumented types behave exactly like the std ones, so the rest of the crate doesn't care whether it runs under the model or not
+use std::{
+    any::Any,
+    collections::HashSet,
+    ops::{Deref, DerefMut},
+    panic::{self, AssertUnwindSafe},
+    sync::{self, Arc, LockResult, PoisonError},
+    thread,
+    time::Duration,
+};
+
+const PREEMPTION_BOUND: usize = 2;
+
+// Runs `f` once for every schedule of the threads it spawns through model::spawn(). Panics with the failing schedule if `f` panics or deadlocks in any of them
+pub(crate) fn model<F>(f: F)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    let f = Arc::new(f);
+    let mut schedule = Vec::new();
+    let mut runs = 0;
+    loop {
+        runs += 1;
+        let scheduler = Arc::new(Scheduler::new(schedule));
+        let f = Arc::clone(&f);
+        scheduler.spawn(move || f());
+
+        let execution = scheduler.wait_until_done();
+        if let Some(failure) = execution.failure {
+            let choices: Vec<usize> = execution.schedule.iter().map(|b| b.chosen).collect();
+            // The threads of a failed run are stuck waiting for their turn and are simply leaked
+            panic!("{failure} (run {runs}, schedule {choices:?})");
+        }
+        for handle in std::mem::take(&mut *scheduler.handles.lock().unwrap()) {
+            handle.join().unwrap();
+        }
+
+        schedule = match next_schedule(execution.schedule) {
+            Some(schedule) => schedule,
+            None => return,
+        };
+    }
+}
+
+// Depth first: bump the last decision that still has an untried alternative and forget about everything after it
+fn next_schedule(mut schedule: Vec<Branch>) -> Option<Vec<Branch>> {
+    while let Some(last) = schedule.last_mut() {
+        if last.chosen + 1 < last.options {
+            last.chosen += 1;
+            return Some(schedule);
+        }
+        schedule.pop();
+    }
+    None
+}
+
+// Like thread::spawn(), but the new thread is run by the scheduler. Must be called from within model()
+pub(crate) fn spawn<F, T>(f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let (scheduler, _) = current().expect("model::spawn() called outside of model()");
+    let result = Arc::new(sync::Mutex::new(None));
+    let slot = Arc::clone(&result);
+    let id = scheduler.spawn(move || {
+        let value = f();
+        *slot.lock().unwrap() = Some(value);
+    });
+    JoinHandle { id, result }
+}
+
+pub(crate) struct JoinHandle<T> {
+    id: usize,
+    result: Arc<sync::Mutex<Option<T>>>,
+}
+
+impl<T> JoinHandle<T> {
+    pub(crate) fn join(self) -> T {
+        let (scheduler, me) = current().expect("JoinHandle::join() called outside of model()");
+        let mut execution = scheduler.execution.lock().unwrap();
+        execution.threads[me] = State::Joining(self.id);
+        drop(scheduler.switch(me, execution));
+
+        let value = self.result.lock().unwrap().take();
+        value.expect("joined thread didn't finish")
+    }
+}
+
+thread_local! {
+    // The scheduler running this thread and the thread's id in it, if this is a model thread
+    static CURRENT: std::cell::RefCell<Option<(Arc<Scheduler>, usize)>> = const { std::cell::RefCell::new(None) };
+}
+
+fn current() -> Option<(Arc<Scheduler>, usize)> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum State {
+    Runnable,
+    Locking(usize), // Blocked until the mutex at this address is free
+    Waiting {
+        condvar: usize,
+        mutex: usize,
+        timed: bool,
+    },
+    Joining(usize),
+    Finished,
+}
+
+// One decision of the scheduler: which of `options` alternatives was picked
+#[derive(Debug)]
+struct Branch {
+    chosen: usize,
+    options: usize,
+}
+
+struct Execution {
+    threads: Vec<State>,
+    timed_out: Vec<bool>,
+    held: HashSet<usize>, // Addresses of the mutexes that are currently locked
+    active: usize,        // The only thread that is allowed to run
+    preemptions: usize,
+    schedule: Vec<Branch>,
+    position: usize, // Decisions before this one are replayed from the previous run
+    failure: Option<String>,
+}
+
+impl Execution {
+    fn new(schedule: Vec<Branch>) -> Self {
+        Execution {
+            threads: Vec::new(),
+            timed_out: Vec::new(),
+            held: HashSet::new(),
+            active: 0,
+            preemptions: 0,
+            schedule,
+            position: 0,
+            failure: None,
+        }
+    }
+
+    fn is_enabled(&self, thread: usize) -> bool {
+        match self.threads[thread] {
+            State::Runnable => true,
+            State::Locking(mutex) => !self.held.contains(&mutex),
+            State::Joining(other) => self.threads[other] == State::Finished,
+            State::Waiting { .. } | State::Finished => false,
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.threads.iter().all(|&state| state == State::Finished)
+    }
+
+    fn choose(&mut self, options: usize) -> usize {
+        if options == 1 {
+            return 0;
+        }
+        self.position += 1;
+        match self.schedule.get(self.position - 1) {
+            Some(branch) if branch.options == options => branch.chosen,
+            Some(_) => {
+                self.failure = Some("the model is not deterministic, a replayed decision had a different number of options".to_string());
+                0
+            }
+            None => {
+                self.schedule.push(Branch { chosen: 0, options });
+                0
+            }
+        }
+    }
+
+    // Picks the thread to run after `me` and makes it the active one. Sets `failure` if no thread can make progress
+    fn pick_next(&mut self, me: usize) {
+        loop {
+            let enabled: Vec<usize> = (0..self.threads.len())
+                .filter(|&thread| self.is_enabled(thread))
+                .collect();
+
+            if enabled.is_empty() {
+                if self.is_finished() {
+                    return;
+                }
+                // Only a timeout can get us out of here
+                let timed: Vec<usize> = (0..self.threads.len())
+                    .filter(|&thread| {
+                        matches!(self.threads[thread], State::Waiting { timed: true, .. })
+                    })
+                    .collect();
+                if timed.is_empty() {
+                    self.failure = Some(format!("deadlock, threads: {:?}", self.threads));
+                    return;
+                }
+                let thread = timed[self.choose(timed.len())];
+                if let State::Waiting { mutex, .. } = self.threads[thread] {
+                    self.threads[thread] = State::Locking(mutex);
+                    self.timed_out[thread] = true;
+                }
+                continue;
+            }
+
+            // Keeping `me` running is always the first option, switching away from it counts as a preemption
+            let me_enabled = enabled.contains(&me);
+            let next = if me_enabled && self.preemptions >= PREEMPTION_BOUND {
+                me
+            } else {
+                let mut options = enabled;
+                if me_enabled {
+                    options.retain(|&thread| thread != me);
+                    options.insert(0, me);
+                }
+                let next = options[self.choose(options.len())];
+                if me_enabled && next != me {
+                    self.preemptions += 1;
+                }
+                next
+            };
+
+            match self.threads[next] {
+                State::Locking(mutex) => {
+                    self.held.insert(mutex);
+                    self.threads[next] = State::Runnable;
+                }
+                State::Joining(_) => self.threads[next] = State::Runnable,
+                _ => {}
+            }
+            self.active = next;
+            return;
+        }
+    }
+}
+
+struct Scheduler {
+    execution: sync::Mutex<Execution>,
+    turn: sync::Condvar, // Notified whenever the active thread changes or the run is over
+    handles: sync::Mutex<Vec<thread::JoinHandle<()>>>,
+}
+
+impl Scheduler {
+    fn new(schedule: Vec<Branch>) -> Self {
+        Scheduler {
+            execution: sync::Mutex::new(Execution::new(schedule)),
+            turn: sync::Condvar::new(),
+            handles: sync::Mutex::new(Vec::new()),
+        }
+    }
+
+    // Registers a new thread. It only starts running once the scheduler picks it
+    fn spawn(self: &Arc<Self>, f: impl FnOnce() + Send + 'static) -> usize {
+        let mut execution = self.execution.lock().unwrap();
+        let id = execution.threads.len();
+        execution.threads.push(State::Runnable);
+        execution.timed_out.push(false);
+        drop(execution);
+
+        let scheduler = Arc::clone(self);
+        let handle = thread::spawn(move || {
+            CURRENT.with(|current| *current.borrow_mut() = Some((Arc::clone(&scheduler), id)));
+            drop(scheduler.wait_for_turn(id, scheduler.execution.lock().unwrap()));
+
+            let result = panic::catch_unwind(AssertUnwindSafe(f));
+            CURRENT.with(|current| *current.borrow_mut() = None);
+            scheduler.finish(id, result.err());
+        });
+        self.handles.lock().unwrap().push(handle);
+        id
+    }
+
+    fn finish(&self, me: usize, panic: Option<Box<dyn Any + Send>>) {
+        let mut execution = self.execution.lock().unwrap();
+        execution.threads[me] = State::Finished;
+        if let Some(panic) = panic {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            execution.failure = Some(format!("thread {me} panicked: {message}"));
+        } else {
+            execution.pick_next(me);
+        }
+        self.turn.notify_all();
+    }
+
+    fn wait_until_done(&self) -> Execution {
+        let mut execution = self.execution.lock().unwrap();
+        while execution.failure.is_none() && !execution.is_finished() {
+            execution = self.turn.wait(execution).unwrap();
+        }
+        std::mem::replace(&mut *execution, Execution::new(Vec::new()))
+    }
+
+    // Lets the scheduler pick who runs next (possibly `me` again) and blocks until it's our turn
+    fn switch<'a>(
+        &'a self,
+        me: usize,
+        mut execution: sync::MutexGuard<'a, Execution>,
+    ) -> sync::MutexGuard<'a, Execution> {
+        execution.pick_next(me);
+        self.turn.notify_all();
+        self.wait_for_turn(me, execution)
+    }
+
+    fn wait_for_turn<'a>(
+        &'a self,
+        me: usize,
+        mut execution: sync::MutexGuard<'a, Execution>,
+    ) -> sync::MutexGuard<'a, Execution> {
+        // After a failure nobody gets a turn anymore
+        while execution.active != me || execution.failure.is_some() {
+            execution = self.turn.wait(execution).unwrap();
+        }
+        execution
+    }
+
+    fn acquire(&self, me: usize, mutex: usize) {
+        let mut execution = self.execution.lock().unwrap();
+        execution.threads[me] = State::Locking(mutex);
+        drop(self.switch(me, execution));
+    }
+
+    fn release(&self, mutex: usize) {
+        self.execution.lock().unwrap().held.remove(&mutex);
+    }
+
+    // Releases `mutex` and sleeps until notified (or timed out), then takes `mutex` again. Returns whether it timed out
+    fn wait(&self, me: usize, condvar: usize, mutex: usize, timed: bool) -> bool {
+        let mut execution = self.execution.lock().unwrap();
+        execution.held.remove(&mutex);
+        execution.threads[me] = State::Waiting {
+            condvar,
+            mutex,
+            timed,
+        };
+        execution.timed_out[me] = false;
+        let execution = self.switch(me, execution);
+        execution.timed_out[me]
+    }
+
+    fn notify(&self, me: usize, condvar: usize, all: bool) {
+        // Other threads may run between the unlock and the notify, which is exactly where wakeups tend to get lost
+        let mut execution = self.execution.lock().unwrap();
+        execution.threads[me] = State::Runnable;
+        let mut execution = self.switch(me, execution);
+
+        let waiters: Vec<usize> = (0..execution.threads.len())
+            .filter(|&thread| {
+                matches!(execution.threads[thread], State::Waiting { condvar: c, .. } if c == condvar)
+            })
+            .collect();
+        let woken = match (all, waiters.len()) {
+            (_, 0) => return,
+            (true, _) => waiters,
+            (false, n) => vec![waiters[execution.choose(n)]],
+        };
+        for thread in woken {
+            if let State::Waiting { mutex, .. } = execution.threads[thread] {
+                execution.threads[thread] = State::Locking(mutex);
+            }
+        }
+    }
+}
+
+// Instrumented Mutex
+pub(crate) struct Mutex<T> {
+    inner: sync::Mutex<T>,
+}
+
+pub(crate) struct MutexGuard<'a, T> {
+    lock: &'a Mutex<T>,
+    // Only None while the guard is handed to a condvar
+    guard: Option<sync::MutexGuard<'a, T>>,
+}
+
+impl<T> Mutex<T> {
+    pub(crate) fn new(data: T) -> Self {
+        Mutex {
+            inner: sync::Mutex::new(data),
+        }
+    }
+
+    pub(crate) fn lock(&self) -> LockResult<MutexGuard<'_, T>> {
+        if let Some((scheduler, me)) = current() {
+            scheduler.acquire(me, self.address());
+        }
+        // Under the model the scheduler only lets us through once nobody else holds the lock, so this never blocks
+        let (guard, poisoned) = split(self.inner.lock());
+        let guard = MutexGuard {
+            lock: self,
+            guard: Some(guard),
+        };
+        if poisoned {
+            Err(PoisonError::new(guard))
+        } else {
+            Ok(guard)
+        }
+    }
+
+    pub(crate) fn is_poisoned(&self) -> bool {
+        self.inner.is_poisoned()
+    }
+
+    pub(crate) fn get_mut(&mut self) -> LockResult<&mut T> {
+        self.inner.get_mut()
+    }
+
+    fn address(&self) -> usize {
+        self as *const Self as usize
+    }
+}
+
+// Splits a std LockResult into the value and whether the lock was poisoned, so that it can be rewrapped around our own guard
+fn split<G>(result: LockResult<G>) -> (G, bool) {
+    match result {
+        Ok(guard) => (guard, false),
+        Err(poisoned) => (poisoned.into_inner(), true),
+    }
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.guard.as_ref().unwrap()
+    }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.guard.as_mut().unwrap()
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        if let Some(guard) = self.guard.take() {
+            drop(guard);
+            if let Some((scheduler, _)) = current() {
+                scheduler.release(self.lock.address());
+            }
+        }
+    }
+}
+
+// Instrumented Condvar
+pub(crate) struct Condvar {
+    inner: sync::Condvar,
+}
+
+pub(crate) struct WaitTimeoutResult(bool);
+
+impl WaitTimeoutResult {
+    #[allow(dead_code)]
+    pub(crate) fn timed_out(&self) -> bool {
+        self.0
+    }
+}
+
+impl Condvar {
+    pub(crate) fn new() -> Self {
+        Condvar {
+            inner: sync::Condvar::new(),
+        }
+    }
+
+    pub(crate) fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> LockResult<MutexGuard<'a, T>> {
+        self.wait_inner(guard, None)
+            .map(|(guard, _)| guard)
+            .map_err(|poisoned| PoisonError::new(poisoned.into_inner().0))
+    }
+
+    pub(crate) fn wait_timeout<'a, T>(
+        &self,
+        guard: MutexGuard<'a, T>,
+        timeout: Duration,
+    ) -> LockResult<(MutexGuard<'a, T>, WaitTimeoutResult)> {
+        self.wait_inner(guard, Some(timeout))
+    }
+
+    fn wait_inner<'a, T>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        timeout: Option<Duration>,
+    ) -> LockResult<(MutexGuard<'a, T>, WaitTimeoutResult)> {
+        let lock = guard.lock;
+        let inner = guard.guard.take().unwrap();
+
+        let ((inner, timed_out), poisoned) = match (current(), timeout) {
+            (None, None) => {
+                let (inner, poisoned) = split(self.inner.wait(inner));
+                ((inner, false), poisoned)
+            }
+            (None, Some(timeout)) => {
+                let ((inner, result), poisoned) = split(self.inner.wait_timeout(inner, timeout));
+                ((inner, result.timed_out()), poisoned)
+            }
+            (Some((scheduler, me)), _) => {
+                drop(inner);
+                let timed_out =
+                    scheduler.wait(me, self.address(), lock.address(), timeout.is_some());
+                if let Some(timeout) = timeout.filter(|_| timed_out) {
+                    // Let the time actually pass, so that the caller's own deadline checks agree that it's over
+                    thread::sleep(timeout);
+                }
+                // The scheduler only hands the lock back once nobody else holds it, so this never blocks
+                let (inner, poisoned) = split(lock.inner.lock());
+                ((inner, timed_out), poisoned)
+            }
+        };
+
+        let result = (
+            MutexGuard {
+                lock,
+                guard: Some(inner),
+            },
+            WaitTimeoutResult(timed_out),
+        );
+        if poisoned {
+            Err(PoisonError::new(result))
+        } else {
+            Ok(result)
+        }
+    }
+
+    pub(crate) fn notify_one(&self) {
+        match current() {
+            Some((scheduler, me)) => scheduler.notify(me, self.address(), false),
+            None => self.inner.notify_one(),
+        }
+    }
+
+    pub(crate) fn notify_all(&self) {
+        match current() {
+            Some((scheduler, me)) => scheduler.notify(me, self.address(), true),
+            None => self.inner.notify_all(),
+        }
+    }
+
+    fn address(&self) -> usize {
+        self as *const Self as usize
+    }
+}
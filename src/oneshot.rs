@@ -0,0 +1,215 @@
+// Oneshot channel for sending a single value, e.g. a reply. Instead of the queue, mutex and condvar of the regular channel, the whole handshake between the two ends goes through one atomic state byte next to a slot for the value and a slot for the reciever's waker
+use std::{
+    cell::UnsafeCell,
+    future::Future,
+    mem::{ManuallyDrop, MaybeUninit},
+    pin::Pin,
+    ptr,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Waker},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{future::ThreadWaker, RecvError, RecvTimeoutError, SendError, TryRecvError};
+
+// The sender is done, either because it sent the value or because it was dropped without sending
+const COMPLETE: u8 = 0b0001;
+// The value slot is initialized
+const VALUE: u8 = 0b0010;
+// The waker slot holds the reciever's waker. While this bit is set the slot is read-only, the reciever has to clear it before swapping in a new waker
+const WAKER_SET: u8 = 0b0100;
+// The reciever has been dropped
+const RECEIVER_DROPPED: u8 = 0b1000;
+
+// Sender
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Drop for Sender<T> {
+    // Only runs if the sender goes away without sending, send() skips it
+    fn drop(&mut self) {
+        let prev = self.shared.state.fetch_or(COMPLETE, Ordering::AcqRel);
+        self.shared.wake_receiver(prev);
+    }
+}
+
+impl<T> Sender<T> {
+    // Consumes the sender, so a value can be sent at most once. Fails (handing the value back) if the reciever is already gone
+    pub fn send(self, data: T) -> Result<(), SendError<T>> {
+        // Our Drop would mark the channel as complete without a value, so move the Arc out without running it
+        let this = ManuallyDrop::new(self);
+        // SAFETY: `this` is never used or dropped again
+        let shared = unsafe { ptr::read(&this.shared) };
+
+        // SAFETY: until COMPLETE is set nobody but us touches the value slot
+        unsafe { (*shared.value.get()).write(data) };
+        let prev = shared.state.fetch_or(COMPLETE | VALUE, Ordering::AcqRel);
+
+        if prev & RECEIVER_DROPPED != 0 {
+            // SAFETY: the reciever is gone, so we are the only one left who could read the value
+            let data = unsafe { (*shared.value.get()).assume_init_read() };
+            shared.state.fetch_and(!VALUE, Ordering::Relaxed);
+            return Err(SendError::Disconnected(data));
+        }
+
+        shared.wake_receiver(prev);
+        Ok(())
+    }
+}
+
+// Reciever
+pub struct Reciever<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Drop for Reciever<T> {
+    fn drop(&mut self) {
+        // A value that was sent but never received is dropped along with Shared
+        self.shared
+            .state
+            .fetch_or(RECEIVER_DROPPED, Ordering::AcqRel);
+    }
+}
+
+impl<T> Future for Reciever<T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.get_mut().poll_recv(cx.waker())
+    }
+}
+
+impl<T> Reciever<T> {
+    // Blocks until the value arrives. Fails if the sender was dropped without sending
+    pub fn recv(mut self) -> Result<T, RecvError> {
+        self.recv_until(None).map_err(|_| RecvError::Disconnected)
+    }
+
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        // A timeout too big to be represented as an Instant is as good as waiting forever
+        let deadline = Instant::now().checked_add(timeout);
+        self.recv_until(deadline)
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let state = self.shared.state.load(Ordering::Acquire);
+        if state & COMPLETE == 0 {
+            return Err(TryRecvError::Empty);
+        }
+        self.take(state).map_err(|_| TryRecvError::Disconnected)
+    }
+
+    // The blocking flavours are the async one driven by a waker that unparks this thread
+    fn recv_until(&mut self, deadline: Option<Instant>) -> Result<T, RecvTimeoutError> {
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        loop {
+            if let Poll::Ready(result) = self.poll_recv(&waker) {
+                return result.map_err(|_| RecvTimeoutError::Disconnected);
+            }
+            // park() may return spuriously, in which case we just check again
+            match deadline {
+                None => thread::park(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(RecvTimeoutError::Timeout);
+                    }
+                    thread::park_timeout(deadline - now);
+                }
+            }
+        }
+    }
+
+    fn poll_recv(&mut self, waker: &Waker) -> Poll<Result<T, RecvError>> {
+        let shared = &*self.shared;
+        let mut state = shared.state.load(Ordering::Acquire);
+        if state & COMPLETE != 0 {
+            return Poll::Ready(self.take(state));
+        }
+
+        if state & WAKER_SET != 0 {
+            // SAFETY: reading is fine while WAKER_SET is set, the sender only ever reads the slot too
+            if unsafe { (*shared.waker.get()).as_ref() }.is_some_and(|w| w.will_wake(waker)) {
+                return Poll::Pending;
+            }
+            // Take the slot back before replacing the waker. If the sender completed in the meantime it may be reading the old waker right now, so leave it alone
+            state = shared.state.fetch_and(!WAKER_SET, Ordering::AcqRel);
+            if state & COMPLETE != 0 {
+                return Poll::Ready(self.take(state));
+            }
+        }
+
+        // SAFETY: WAKER_SET is clear, so the sender won't look at the slot
+        unsafe { *shared.waker.get() = Some(waker.clone()) };
+        state = shared.state.fetch_or(WAKER_SET, Ordering::AcqRel);
+        if state & COMPLETE != 0 {
+            // The sender completed before it could see our waker, so nobody is going to wake us up
+            return Poll::Ready(self.take(state));
+        }
+        Poll::Pending
+    }
+
+    // Must only be called once COMPLETE is set in `state`
+    fn take(&mut self, state: u8) -> Result<T, RecvError> {
+        if state & VALUE == 0 {
+            return Err(RecvError::Disconnected);
+        }
+        // SAFETY: VALUE is set and the sender is done with the slot. Clearing the bit makes sure the value isn't read (or dropped) twice
+        let data = unsafe { (*self.shared.value.get()).assume_init_read() };
+        self.shared.state.fetch_and(!VALUE, Ordering::Relaxed);
+        Ok(data)
+    }
+}
+
+// Channel
+struct Shared<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+    waker: UnsafeCell<Option<Waker>>,
+}
+
+// SAFETY: access to the two slots is arbitrated by `state`, see the bits above
+unsafe impl<T: Send> Send for Shared<T> {}
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    // `prev` is the state from before the sender set COMPLETE
+    fn wake_receiver(&self, prev: u8) {
+        if prev & WAKER_SET != 0 && prev & RECEIVER_DROPPED == 0 {
+            // SAFETY: WAKER_SET was set when we completed, so the reciever won't write the slot anymore
+            if let Some(waker) = unsafe { (*self.waker.get()).as_ref() } {
+                waker.wake_by_ref();
+            }
+        }
+    }
+}
+
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() & VALUE != 0 {
+            // SAFETY: the value was sent but never received
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+pub fn channel<T>() -> (Sender<T>, Reciever<T>) {
+    let shared = Arc::new(Shared {
+        state: AtomicU8::new(0),
+        value: UnsafeCell::new(MaybeUninit::uninit()),
+        waker: UnsafeCell::new(None),
+    });
+
+    let sender = Sender {
+        shared: shared.clone(),
+    };
+
+    let receiver = Reciever { shared };
+
+    (sender, receiver)
+}
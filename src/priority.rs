@@ -0,0 +1,261 @@
+// Priority channel: messages with a higher priority are delivered first, messages with the same priority in the order they were sent. With aging enabled a message gains one priority level for every `interval` it has been waiting, so a steady stream of urgent messages can't starve the bulk work forever
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Condvar, Mutex, MutexGuard, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{RecvError, SendError, TryRecvError};
+
+// Sender
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        let mut inner = self.shared.lock();
+        inner.senders_count += 1;
+
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut inner = self.shared.lock();
+        inner.senders_count -= 1;
+        if inner.senders_count == 0 {
+            drop(inner);
+            self.shared.available.notify_one();
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    // Sends with the lowest priority
+    pub fn send(&self, data: T) -> Result<(), SendError<T>> {
+        self.send_with_priority(data, 0)
+    }
+
+    // Higher `priority` means more urgent
+    pub fn send_with_priority(&self, data: T, priority: u32) -> Result<(), SendError<T>> {
+        let mut channel = self.shared.lock();
+        if !channel.is_channel_still_active {
+            return Err(SendError::Disconnected(data));
+        }
+
+        channel.push_back(Entry {
+            data,
+            priority,
+            enqueued_at: Instant::now(),
+        });
+        // Let the reciever know that its buffer may no longer hold the most urgent messages
+        self.shared
+            .highest_pending
+            .fetch_max(priority as u64 + 1, Ordering::Release);
+        drop(channel);
+        self.shared.available.notify_one();
+        Ok(())
+    }
+}
+
+// Reciever
+pub struct Reciever<T> {
+    shared: Arc<Shared<T>>,
+    // Same idea as the regular reciever's buffer: take everything that is queued in one go (already in delivery order) so that the next recv() calls don't need the lock. A message sent afterwards may be more urgent than what's buffered though, so recv() checks `highest_pending` before serving from the buffer
+    buffer: VecDeque<Entry<T>>,
+}
+
+impl<T> Drop for Reciever<T> {
+    fn drop(&mut self) {
+        let mut inner = self.shared.lock();
+        inner.is_channel_still_active = false;
+    }
+}
+
+impl<T> Iterator for Reciever<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv().ok()
+    }
+}
+
+impl<T> Reciever<T> {
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        if let Some(data) = self.pop_buffer() {
+            return Ok(data);
+        }
+
+        let mut channel = self.shared.lock();
+        loop {
+            if let Some(data) = channel.take_into(&mut self.buffer) {
+                // Everything queued is now in our buffer
+                self.shared.highest_pending.store(0, Ordering::Release);
+                return Ok(data);
+            }
+            if channel.senders_count == 0 {
+                return Err(RecvError::Disconnected);
+            }
+            channel = self
+                .shared
+                .available
+                .wait(channel)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        if let Some(data) = self.pop_buffer() {
+            return Ok(data);
+        }
+
+        let mut channel = self.shared.lock();
+        match channel.take_into(&mut self.buffer) {
+            Some(data) => {
+                self.shared.highest_pending.store(0, Ordering::Release);
+                Ok(data)
+            }
+            None if channel.senders_count == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    // Serves from the buffer unless something more urgent than its front has been sent since we filled it
+    fn pop_buffer(&mut self) -> Option<T> {
+        let front = self.buffer.front()?;
+        if self.shared.highest_pending.load(Ordering::Acquire) > front.priority as u64 + 1 {
+            return None;
+        }
+        self.buffer.pop_front().map(|entry| entry.data)
+    }
+}
+
+// Channel
+struct Shared<T> {
+    inner: Mutex<Inner<T>>,
+    available: Condvar,
+    highest_pending: AtomicU64, // 1 + the highest priority sent since the reciever last emptied the queue, 0 if nothing was. Lets the reciever check its buffer against new arrivals without taking the lock
+}
+
+impl<T> Shared<T> {
+    // Nothing of the user's runs under this lock, messages are only moved around, so it shouldn't ever get poisoned. Recover anyway rather than have Drop panic on it
+    fn lock(&self) -> MutexGuard<'_, Inner<T>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+struct Entry<T> {
+    data: T,
+    priority: u32,
+    enqueued_at: Instant,
+}
+
+struct Inner<T> {
+    levels: BTreeMap<u32, VecDeque<Entry<T>>>, // One FIFO queue per priority, which gives the stable ordering within a level for free
+    aging: Option<Duration>,
+    senders_count: usize,
+    is_channel_still_active: bool,
+}
+
+impl<T> Inner<T> {
+    fn push_back(&mut self, entry: Entry<T>) {
+        self.levels
+            .entry(entry.priority)
+            .or_default()
+            .push_back(entry);
+    }
+
+    fn push_front(&mut self, entry: Entry<T>) {
+        self.levels
+            .entry(entry.priority)
+            .or_default()
+            .push_front(entry);
+    }
+
+    // Puts the reciever's buffer back (it may have been skipped because of a more urgent message), then pops the most urgent message and moves the rest into the buffer in delivery order
+    fn take_into(&mut self, buffer: &mut VecDeque<Entry<T>>) -> Option<T> {
+        // The buffer is older than anything in the queue, so it goes back to the front of its levels
+        while let Some(entry) = buffer.pop_back() {
+            self.push_front(entry);
+        }
+
+        let now = Instant::now();
+        let data = self.pop_most_urgent(now)?.data;
+        while let Some(entry) = self.pop_most_urgent(now) {
+            buffer.push_back(entry);
+        }
+        Some(data)
+    }
+
+    fn pop_most_urgent(&mut self, now: Instant) -> Option<Entry<T>> {
+        let priority = match self.aging {
+            // Without aging that's simply the front of the highest level
+            None => *self.levels.keys().next_back()?,
+            // With aging only the front of each level needs to be looked at, since it has waited the longest in that level. Ties go to the one that has waited longer
+            Some(interval) => {
+                let (priority, _) = self
+                    .levels
+                    .iter()
+                    .filter_map(|(&priority, level)| level.front().map(|entry| (priority, entry)))
+                    .max_by_key(|(_, entry)| {
+                        let waited = now.saturating_duration_since(entry.enqueued_at);
+                        let boost = (waited.as_nanos() / interval.as_nanos().max(1)) as u64;
+                        (
+                            entry.priority as u64 + boost,
+                            std::cmp::Reverse(entry.enqueued_at),
+                        )
+                    })?;
+                priority
+            }
+        };
+
+        let level = self.levels.get_mut(&priority)?;
+        let entry = level.pop_front();
+        if level.is_empty() {
+            self.levels.remove(&priority);
+        }
+        entry
+    }
+}
+
+pub fn channel<T>() -> (Sender<T>, Reciever<T>) {
+    new_channel(None)
+}
+
+// Like channel(), but every `interval` a message has been waiting raises its priority by one
+pub fn channel_with_aging<T>(interval: Duration) -> (Sender<T>, Reciever<T>) {
+    new_channel(Some(interval))
+}
+
+fn new_channel<T>(aging: Option<Duration>) -> (Sender<T>, Reciever<T>) {
+    let inner = Inner {
+        levels: BTreeMap::new(),
+        aging,
+        senders_count: 1,
+        is_channel_still_active: true,
+    };
+
+    let shared = Arc::new(Shared {
+        inner: Mutex::new(inner),
+        available: Condvar::new(),
+        highest_pending: AtomicU64::new(0),
+    });
+
+    let sender = Sender {
+        shared: shared.clone(),
+    };
+
+    let receiver = Reciever {
+        shared,
+        buffer: VecDeque::new(),
+    };
+
+    (sender, receiver)
+}
@@ -0,0 +1,96 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{RateLimitError, Sender};
+
+// Sender that lets through at most `per_second` messages per second on average, with bursts of up to `burst` messages. Classic token bucket: every message takes a token, tokens come back at `per_second` and the bucket holds at most `burst` of them. It starts out full
+//
+// The bucket belongs to this wrapper, not to the channel. Two RateLimitedSenders on the same channel get a budget each
+//
+// let mut tx = RateLimitedSender::new(tx, 10_000, 500).mode(RateLimitMode::Reject);
+pub struct RateLimitedSender<T> {
+    sender: Sender<T>,
+    per_second: f64,
+    burst: f64,
+    tokens: f64,
+    refilled_at: Instant,
+    mode: RateLimitMode,
+}
+
+// What send() does when the bucket is empty
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitMode {
+    // Sleep until the next token comes in
+    #[default]
+    Wait,
+    // Fail right away with RateLimitError::RateLimited
+    Reject,
+    // Fail right away with RateLimitError::RetryAfter, which says how long the caller has to back off
+    RetryAfter,
+}
+
+impl<T> RateLimitedSender<T> {
+    // Panics if `per_second` is 0, the bucket would never refill. A `burst` of 0 is treated as 1
+    pub fn new(sender: Sender<T>, per_second: u32, burst: u32) -> Self {
+        assert!(
+            per_second > 0,
+            "rate limit must allow at least one message per second"
+        );
+        let burst = burst.max(1) as f64;
+        RateLimitedSender {
+            sender,
+            per_second: per_second as f64,
+            burst,
+            tokens: burst,
+            refilled_at: Instant::now(),
+            mode: RateLimitMode::Wait,
+        }
+    }
+
+    pub fn mode(mut self, mode: RateLimitMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    // Takes a token and sends `data`. Only a message the channel accepted uses up a token, so an error never eats into the budget
+    pub fn send(&mut self, data: T) -> Result<(), RateLimitError<T>> {
+        self.refill();
+        while self.tokens < 1.0 {
+            let wait = self.next_token_in();
+            match self.mode {
+                RateLimitMode::Wait => thread::sleep(wait),
+                RateLimitMode::Reject => return Err(RateLimitError::RateLimited(data)),
+                RateLimitMode::RetryAfter => return Err(RateLimitError::RetryAfter(data, wait)),
+            }
+            // Sleeping may come up a little short, hence the loop
+            self.refill();
+        }
+
+        self.sender.send(data)?;
+        self.tokens -= 1.0;
+        Ok(())
+    }
+
+    // How long until send() would go through without waiting. Zero if there's a token right now
+    pub fn next_token_in(&mut self) -> Duration {
+        self.refill();
+        match self.tokens >= 1.0 {
+            true => Duration::ZERO,
+            false => Duration::from_secs_f64((1.0 - self.tokens) / self.per_second),
+        }
+    }
+
+    pub fn get_ref(&self) -> &Sender<T> {
+        &self.sender
+    }
+
+    // Adds the tokens that came in since the last refill
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.burst);
+        self.refilled_at = now;
+    }
+}
@@ -0,0 +1,211 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    sync::Arc,
+    task::Waker,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    future::{register, ThreadWaker},
+    Reciever, SelectTimeoutError, TrySelectError,
+};
+
+// Waits on several recievers at once. Recievers are added with recv(), which returns the index that ready() reports back once that reciever has a message or all of its senders are gone. Select only tells which one is ready, the message itself is then taken with try_recv() on that reciever (the select! macro does all of this for you)
+//
+// let mut sel = Select::new();
+// let control = sel.recv(&control_rx);
+// let data = sel.recv(&data_rx);
+// match sel.ready() {
+//     i if i == control => handle_control(control_rx.try_recv()),
+//     i if i == data => handle_data(data_rx.try_recv()),
+//     _ => unreachable!(),
+// }
+pub struct Select<'a> {
+    handles: Vec<&'a dyn Selectable>,
+}
+
+// Type erased view of a Reciever<T>, so that recievers of different message types can be selected over together
+trait Selectable {
+    // Checks whether a message (or the disconnect) is available, and if not registers the waker under the same lock so that a send can't slip in between
+    fn ready_or_register(&self, waker: &Waker) -> bool;
+    fn unregister(&self, waker: &Waker);
+}
+
+impl<T> Selectable for Reciever<T> {
+    fn ready_or_register(&self, waker: &Waker) -> bool {
+        if !self.buffer.is_empty() {
+            return true;
+        }
+        let mut channel = self.shared.lock();
+        if !channel.queue.is_empty() || channel.is_disconnected() || self.shared.is_poisoned() {
+            return true;
+        }
+        register(&mut channel.recv_wakers, waker);
+        false
+    }
+
+    fn unregister(&self, waker: &Waker) {
+        let mut channel = self.shared.lock();
+        channel
+            .recv_wakers
+            .retain(|registered| !registered.will_wake(waker));
+    }
+}
+
+impl Default for Select<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Select<'a> {
+    pub fn new() -> Self {
+        Select {
+            handles: Vec::new(),
+        }
+    }
+
+    // Adds a reciever and returns its index
+    pub fn recv<T>(&mut self, reciever: &'a Reciever<T>) -> usize {
+        self.handles.push(reciever);
+        self.handles.len() - 1
+    }
+
+    // Blocks until one of the recievers is ready and returns its index
+    pub fn ready(&mut self) -> usize {
+        self.ready_until(None)
+            .expect("select without a deadline can't time out")
+    }
+
+    // Returns the index of a reciever that is ready right now, without blocking
+    pub fn try_ready(&mut self) -> Result<usize, TrySelectError> {
+        self.ready_until(Some(Instant::now())).ok_or(TrySelectError)
+    }
+
+    pub fn ready_timeout(&mut self, timeout: Duration) -> Result<usize, SelectTimeoutError> {
+        // A timeout too big to be represented as an Instant is as good as waiting forever
+        match Instant::now().checked_add(timeout) {
+            Some(deadline) => self.ready_deadline(deadline),
+            None => Ok(self.ready()),
+        }
+    }
+
+    pub fn ready_deadline(&mut self, deadline: Instant) -> Result<usize, SelectTimeoutError> {
+        self.ready_until(Some(deadline)).ok_or(SelectTimeoutError)
+    }
+
+    fn ready_until(&mut self, deadline: Option<Instant>) -> Option<usize> {
+        assert!(!self.handles.is_empty(), "select with no recievers");
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+
+        let ready = loop {
+            // Registering on every reciever while checking them means a message sent to any of them after this pass unparks us
+            let ready: Vec<usize> = (0..self.handles.len())
+                .filter(|&i| self.handles[i].ready_or_register(&waker))
+                .collect();
+            if !ready.is_empty() {
+                // Pick a random one when several are ready, so that a busy reciever added first can't starve the others
+                break Some(ready[random_below(ready.len())]);
+            }
+
+            match deadline {
+                None => thread::park(),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break None;
+                    }
+                    thread::park_timeout(deadline - now);
+                }
+            }
+            // park() may return spuriously or because of a stale wakeup, in which case we just check again
+        };
+
+        // Don't leave our waker behind in the recievers that didn't fire
+        for handle in &self.handles {
+            handle.unregister(&waker);
+        }
+        ready
+    }
+}
+
+// std has no random number generator, but RandomState is seeded randomly and with different keys each time
+fn random_below(n: usize) -> usize {
+    if n == 1 {
+        return 0;
+    }
+    (RandomState::new().build_hasher().finish() % n as u64) as usize
+}
+
+// Waits on several recievers at once and runs the arm of the one that got a message (or got disconnected), with `msg` bound to a Result<T, RecvError>. Recievers have to be place expressions (e.g. variables), since they are borrowed more than once
+//
+// select! {
+//     recv(control_rx) -> msg => handle_control(msg),
+//     recv(data_rx) -> msg => handle_data(msg),
+// }
+//
+// An optional last arm `default => ...` runs if none of them is ready right now, and `default(timeout) => ...` runs if none of them becomes ready within the timeout
+#[macro_export]
+macro_rules! select {
+    (@loop $label:lifetime, $wait:expr, [$(recv($rx:expr) -> $msg:pat => $body:expr),+], $none:expr) => {
+        $label: loop {
+            let ready = {
+                let mut sel = $crate::Select::new();
+                $( sel.recv(&$rx); )+
+                ($wait)(&mut sel)
+            };
+            let ready: usize = match ready {
+                ::core::option::Option::Some(ready) => ready,
+                ::core::option::Option::None => $none,
+            };
+            let mut index = 0usize..;
+            $(
+                if index.next() == ::core::option::Option::Some(ready) {
+                    match $rx.try_recv() {
+                        // Another clone of the reciever got to the message first, go back to waiting
+                        ::core::result::Result::Err($crate::TryRecvError::Empty) => continue $label,
+                        res => {
+                            let $msg = res.map_err(|err| match err {
+                                $crate::TryRecvError::Poisoned => $crate::RecvError::Poisoned,
+                                _ => $crate::RecvError::Disconnected,
+                            });
+                            break $label $body;
+                        }
+                    }
+                }
+            )+
+            unreachable!("select returned an unknown index")
+        }
+    };
+    ($(recv($rx:expr) -> $msg:pat => $body:expr),+ $(,)?) => {
+        $crate::select!(
+            @loop 'select,
+            |sel: &mut $crate::Select<'_>| ::core::option::Option::Some(sel.ready()),
+            [$(recv($rx) -> $msg => $body),+],
+            continue 'select
+        )
+    };
+    ($(recv($rx:expr) -> $msg:pat => $body:expr,)+ default => $default:expr $(,)?) => {
+        $crate::select!(
+            @loop 'select,
+            |sel: &mut $crate::Select<'_>| sel.try_ready().ok(),
+            [$(recv($rx) -> $msg => $body),+],
+            break 'select $default
+        )
+    };
+    ($(recv($rx:expr) -> $msg:pat => $body:expr,)+ default($timeout:expr) => $default:expr $(,)?) => {{
+        // Same as Select::ready_timeout(), a timeout too big for an Instant means waiting forever
+        let deadline = ::std::time::Instant::now().checked_add($timeout);
+        $crate::select!(
+            @loop 'select,
+            |sel: &mut $crate::Select<'_>| match deadline {
+                ::core::option::Option::Some(deadline) => sel.ready_deadline(deadline).ok(),
+                ::core::option::Option::None => ::core::option::Option::Some(sel.ready()),
+            },
+            [$(recv($rx) -> $msg => $body),+],
+            break 'select $default
+        )
+    }};
+}
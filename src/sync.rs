@@ -0,0 +1,6 @@
+// Mutex and Condvar used by the core channel. Normally these are just the std ones, but the tests swap in the instrumented versions from model.rs so that every interleaving of the senders and recievers can be explored deterministically
+#[cfg(not(test))]
+pub(crate) use std::sync::{Condvar, Mutex, MutexGuard};
+
+#[cfg(test)]
+pub(crate) use crate::model::{Condvar, Mutex, MutexGuard};
@@ -0,0 +1,1348 @@
+// Tests for the core channel are model checked: they run their scenario under model::model(), which tries every interleaving of the threads (see model.rs), so a lost wakeup shows up as a deadlock and a lost message as a failed assert in at least one of them. Everything else is a plain test: the poisoning tests (a panic under the model is a failure by definition), Select and block_on() (they park the thread themselves instead of going through a Condvar) and the other flavours (they use std's Mutex and Condvar directly)
+use std::{
+    future::Future,
+    panic,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Waker},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    block_on, broadcast, channel, lockfree,
+    model::{model, spawn},
+    oneshot, priority, select, sync_channel, watch, BufferedSender, Builder, OverflowPolicy,
+    PoisonPolicy, RateLimitError, RateLimitMode, RateLimitedSender, Reciever, RecvError,
+    RecvTimeoutError, Select, SendError, TryRecvError, TrySelectError, WaitStrategy,
+};
+
+#[test]
+fn send_then_drop_sender() {
+    model(|| {
+        let (tx, mut rx) = channel();
+        let sender = spawn(move || {
+            tx.send(1).unwrap();
+            tx.send(2).unwrap();
+        });
+
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Ok(2));
+        // Only the drop of the last sender can wake us up here
+        assert_eq!(rx.recv(), Err(RecvError::Disconnected));
+        sender.join();
+    });
+}
+
+#[test]
+fn spinning_reciever_falls_back_to_parking() {
+    model(|| {
+        let (tx, mut rx) = Builder::new()
+            .wait_strategy(WaitStrategy::SpinThenYieldThenPark {
+                spins: 1,
+                yields: 1,
+            })
+            .build();
+        let sender = spawn(move || {
+            tx.send(1).unwrap();
+            tx.send(2).unwrap();
+        });
+
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Ok(2));
+        assert_eq!(rx.recv(), Err(RecvError::Disconnected));
+        sender.join();
+    });
+}
+
+#[test]
+fn cloned_senders() {
+    model(|| {
+        let (tx, mut rx) = channel();
+        let tx2 = tx.clone();
+        let first = spawn(move || tx.send(1).unwrap());
+        let second = spawn(move || tx2.send(2).unwrap());
+
+        let mut received = vec![rx.recv().unwrap(), rx.recv().unwrap()];
+        received.sort();
+        assert_eq!(received, [1, 2]);
+        assert_eq!(rx.recv(), Err(RecvError::Disconnected));
+        first.join();
+        second.join();
+    });
+}
+
+#[test]
+fn clone_races_with_drop() {
+    model(|| {
+        let (tx, mut rx) = channel();
+        let sender = spawn(move || {
+            let tx2 = tx.clone();
+            drop(tx);
+            tx2.send(1).unwrap();
+        });
+
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Err(RecvError::Disconnected));
+        sender.join();
+    });
+}
+
+#[test]
+fn upgrade_races_with_last_sender_drop() {
+    model(|| {
+        let (tx, mut rx) = channel();
+        let weak = tx.downgrade();
+        let upgrader = spawn(move || {
+            if let Some(tx) = weak.upgrade() {
+                tx.send(1).unwrap();
+            }
+        });
+
+        drop(tx);
+        // Either the upgrade won and its message arrives, or it lost and the channel is disconnected right away. It must never sleep forever
+        let mut received = Vec::new();
+        while let Ok(val) = rx.recv() {
+            received.push(val);
+        }
+        assert!(received.len() <= 1);
+        upgrader.join();
+    });
+}
+
+#[test]
+fn bounded_sender_blocks_until_space() {
+    model(|| {
+        let (tx, mut rx) = sync_channel(1);
+        let sender = spawn(move || {
+            for i in 0..3 {
+                tx.send(i).unwrap();
+            }
+        });
+
+        for i in 0..3 {
+            assert_eq!(rx.recv(), Ok(i));
+        }
+        assert_eq!(rx.recv(), Err(RecvError::Disconnected));
+        sender.join();
+    });
+}
+
+#[test]
+fn buffered_messages_count_against_the_capacity() {
+    model(|| {
+        let (tx, mut rx) = sync_channel(2);
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        // 2 moves into the buffer, but keeps its slot until the reciever comes back for the lock
+        assert_eq!(rx.recv(), Ok(1));
+        tx.send(3).unwrap();
+        assert!(rx.shared.lock().is_full());
+
+        let sender = spawn(move || tx.send(4));
+        assert_eq!(rx.recv(), Ok(2));
+        assert_eq!(rx.recv(), Ok(3));
+        assert_eq!(rx.recv(), Ok(4));
+        assert_eq!(sender.join(), Ok(()));
+    });
+}
+
+#[test]
+fn send_all_into_bounded_channel() {
+    model(|| {
+        let (tx, mut rx) = sync_channel(2);
+        let sender = spawn(move || tx.send_all(0..4).unwrap());
+
+        let mut received = Vec::new();
+        while rx.recv_many(&mut received, 3).is_ok() {}
+        assert_eq!(received, [0, 1, 2, 3]);
+        sender.join();
+    });
+}
+
+#[test]
+fn send_all_runs_the_iterator_outside_the_lock() {
+    let (tx, mut rx) = Builder::new().poison_policy(PoisonPolicy::Fail).build();
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        tx.send_all((0..3).map(|i| match i {
+            2 => panic!("iterator blew up"),
+            i => i,
+        }))
+    }));
+    assert!(result.is_err());
+    // Nothing was sent, but the channel isn't poisoned either
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+    // An iterator that uses the channel itself doesn't deadlock
+    tx.send_all((1..3).inspect(|_| assert!(!tx.is_closed())))
+        .unwrap();
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [1, 2]);
+}
+
+#[test]
+fn buffered_sender_flushes_on_drop() {
+    model(|| {
+        let (tx, mut rx) = channel();
+        let sender = spawn(move || {
+            let mut tx = BufferedSender::new(tx, 2);
+            for i in 0..3 {
+                tx.send(i).unwrap();
+            }
+        });
+
+        for i in 0..3 {
+            assert_eq!(rx.recv(), Ok(i));
+        }
+        assert_eq!(rx.recv(), Err(RecvError::Disconnected));
+        sender.join();
+    });
+}
+
+#[test]
+fn buffered_sender_dead_letters_what_it_cant_flush() {
+    let (dead_tx, mut dead) = channel();
+    let (tx, mut rx) = Builder::new()
+        .capacity(1)
+        .overflow_policy(OverflowPolicy::Fail)
+        .dead_letter(move |val| dead_tx.send(val).unwrap())
+        .build();
+    // Only flushes on drop
+    let mut buffered = BufferedSender::new(tx, usize::MAX);
+    for i in 1..=3 {
+        buffered.send(i).unwrap();
+    }
+    assert_eq!(buffered.pending(), 3);
+
+    drop(buffered);
+    assert_eq!(rx.recv(), Ok(1));
+    assert_eq!(rx.recv(), Err(RecvError::Disconnected));
+    assert_eq!(dead.try_iter().collect::<Vec<_>>(), [2, 3]);
+}
+
+#[test]
+fn drop_oldest_never_blocks_the_sender() {
+    model(|| {
+        let (tx, mut rx) = Builder::new()
+            .capacity(1)
+            .overflow_policy(OverflowPolicy::DropOldest)
+            .build();
+        let sender = spawn(move || {
+            for i in 0..3 {
+                tx.send(i).unwrap();
+            }
+            tx.discarded()
+        });
+
+        let mut received = Vec::new();
+        while let Ok(val) = rx.recv() {
+            received.push(val);
+        }
+        let discarded = sender.join();
+        // However much was thrown out on the way, the last message always makes it, and nothing is received twice or out of order
+        assert_eq!(received.last(), Some(&2));
+        assert!(received.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(received.len() as u64 + discarded, 3);
+    });
+}
+
+#[test]
+fn full_channel_fails_or_drops_newest() {
+    let (tx, mut rx) = Builder::new()
+        .capacity(1)
+        .overflow_policy(OverflowPolicy::Fail)
+        .build();
+    tx.send(1).unwrap();
+    assert_eq!(tx.send(2), Err(SendError::Full(2)));
+    assert_eq!(tx.send_all([3, 4]), Err(SendError::Full(vec![3, 4])));
+    assert_eq!(rx.recv(), Ok(1));
+    // Nothing was lost, every rejected message went back to the sender
+    assert_eq!(tx.discarded(), 0);
+
+    let (evict_tx, mut evicted) = channel();
+    let (tx, mut rx) = Builder::new()
+        .capacity(2)
+        .overflow_policy(OverflowPolicy::DropNewest)
+        .on_evict(move |val| evict_tx.send(val).unwrap())
+        .build();
+    tx.send_all(0..5).unwrap();
+    drop(tx);
+    assert_eq!(rx.drain_available(), Ok(vec![0, 1]));
+    assert_eq!(evicted.drain_available(), Ok(vec![2, 3, 4]));
+    assert_eq!(rx.discarded(), 3);
+}
+
+#[test]
+fn rate_limited_sender_rejects_past_the_burst() {
+    let (tx, mut rx) = channel();
+    let mut tx = RateLimitedSender::new(tx, 1, 2).mode(RateLimitMode::Reject);
+    tx.send(1).unwrap();
+    tx.send(2).unwrap();
+    assert_eq!(tx.send(3), Err(RateLimitError::RateLimited(3)));
+    assert_eq!(format!("{:?}", tx.send(3).unwrap_err()), "RateLimited(..)");
+
+    let mut tx = tx.mode(RateLimitMode::RetryAfter);
+    match tx.send(4) {
+        Err(RateLimitError::RetryAfter(4, wait)) => {
+            assert!(wait > Duration::ZERO && wait <= Duration::from_secs(1))
+        }
+        other => panic!("expected RetryAfter, got {other:?}"),
+    }
+
+    drop(tx);
+    assert_eq!(rx.drain_available(), Ok(vec![1, 2]));
+}
+
+#[test]
+fn rendezvous_handoff() {
+    model(|| {
+        let (tx, mut rx) = sync_channel(0);
+        let sender = spawn(move || {
+            tx.send(1).unwrap();
+            tx.send(2).unwrap();
+        });
+
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Ok(2));
+        sender.join();
+    });
+}
+
+#[test]
+fn rendezvous_with_competing_senders() {
+    model(|| {
+        let (tx, mut rx) = sync_channel(0);
+        let tx2 = tx.clone();
+        let first = spawn(move || tx.send(1));
+        let second = spawn(move || tx2.send(2));
+
+        let mut received = vec![rx.recv().unwrap(), rx.recv().unwrap()];
+        received.sort();
+        assert_eq!(received, [1, 2]);
+        assert_eq!(first.join(), Ok(()));
+        assert_eq!(second.join(), Ok(()));
+        assert_eq!(rx.recv(), Err(RecvError::Disconnected));
+    });
+}
+
+#[test]
+fn rendezvous_reciever_dropped() {
+    model(|| {
+        let (tx, rx) = sync_channel(0);
+        let sender = spawn(move || tx.send(1));
+
+        drop(rx);
+        // Nobody took the message, so it has to come back
+        assert_eq!(sender.join(), Err(SendError::Disconnected(1)));
+    });
+}
+
+#[test]
+fn reciever_dropped_while_sender_blocked() {
+    model(|| {
+        let (tx, rx) = sync_channel(1);
+        tx.send(1).unwrap();
+        let sender = spawn(move || tx.send(2));
+
+        drop(rx);
+        assert_eq!(sender.join(), Err(SendError::Disconnected(2)));
+    });
+}
+
+#[test]
+fn cloned_recievers_get_every_message_once() {
+    model(|| {
+        let (tx, mut rx) = channel();
+        let mut rx2 = rx.clone();
+        let other = spawn(move || {
+            let mut received = Vec::new();
+            while let Ok(val) = rx2.recv() {
+                received.push(val);
+            }
+            received
+        });
+
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        drop(tx);
+
+        let mut received = Vec::new();
+        while let Ok(val) = rx.recv() {
+            received.push(val);
+        }
+        received.extend(other.join());
+        received.sort();
+        assert_eq!(received, [1, 2]);
+    });
+}
+
+#[test]
+fn dropping_a_cloned_reciever_frees_its_slots() {
+    model(|| {
+        let (tx, mut rx) = sync_channel(2);
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        // The first recv() moves 2 into the buffer, the second one takes it from there. Its slot stays claimed until rx comes back for the lock
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Ok(2));
+        tx.send(3).unwrap();
+        let mut rx2 = rx.clone();
+        let sender = spawn(move || tx.send(4));
+
+        drop(rx);
+        assert_eq!(sender.join(), Ok(()));
+        assert_eq!(rx2.recv(), Ok(3));
+        assert_eq!(rx2.recv(), Ok(4));
+    });
+}
+
+#[test]
+fn dead_letter_gets_the_buffer_and_the_queue() {
+    model(|| {
+        let (dead_tx, mut dead) = channel();
+        let (tx, mut rx) = Builder::new()
+            .dead_letter(move |val| dead_tx.send(val).unwrap())
+            .build();
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        // 2 is left in the buffer, 3 and 4 in the queue
+        assert_eq!(rx.recv(), Ok(1));
+        tx.send(3).unwrap();
+        tx.send(4).unwrap();
+
+        drop(rx);
+        assert_eq!(dead.try_iter().collect::<Vec<_>>(), [2, 3, 4]);
+        assert_eq!(tx.send(5), Err(SendError::Disconnected(5)));
+    });
+}
+
+#[test]
+fn dead_letter_waits_for_the_last_reciever() {
+    model(|| {
+        let (dead_tx, mut dead) = channel();
+        let (tx, mut rx) = Builder::new()
+            .dead_letter(move |val| dead_tx.send(val).unwrap())
+            .build();
+        let mut rx2 = rx.clone();
+        for i in 1..=5 {
+            tx.send(i).unwrap();
+        }
+        // rx takes its share of the queue into the buffer, 2 and 3. They go back to the front of the queue for rx2
+        assert_eq!(rx.recv(), Ok(1));
+        drop(rx);
+        assert_eq!(dead.try_recv(), Err(TryRecvError::Empty));
+
+        assert_eq!(rx2.recv(), Ok(2));
+        drop(rx2);
+        assert_eq!(dead.try_iter().collect::<Vec<_>>(), [3, 4, 5]);
+    });
+}
+
+#[test]
+fn dead_letter_leaves_rendezvous_messages_to_their_sender() {
+    model(|| {
+        let (dead_tx, mut dead) = channel();
+        let (tx, rx) = Builder::new()
+            .capacity(0)
+            .dead_letter(move |val| dead_tx.send(val).unwrap())
+            .build();
+        let sender = spawn(move || tx.send(1));
+
+        drop(rx);
+        assert_eq!(sender.join(), Err(SendError::Disconnected(1)));
+        // The channel and its hook are gone by now, without the hook ever being called
+        assert_eq!(dead.recv(), Err(RecvError::Disconnected));
+    });
+}
+
+#[test]
+fn last_sender_drop_wakes_every_reciever() {
+    model(|| {
+        let (tx, mut rx) = channel::<i32>();
+        let mut rx2 = rx.clone();
+        let recievers = [spawn(move || rx.recv()), spawn(move || rx2.recv())];
+
+        drop(tx);
+        for reciever in recievers {
+            assert_eq!(reciever.join(), Err(RecvError::Disconnected));
+        }
+    });
+}
+
+#[test]
+fn closed_wakes_up_on_reciever_drop() {
+    model(|| {
+        let (tx, rx) = sync_channel(1);
+        let waiter = spawn(move || {
+            tx.closed();
+            tx.send(1)
+        });
+
+        drop(rx);
+        assert_eq!(waiter.join(), Err(SendError::Disconnected(1)));
+    });
+}
+
+#[test]
+fn close_wakes_up_reciever() {
+    model(|| {
+        let (tx, mut rx) = channel::<i32>();
+        let closer = spawn(move || tx.close_channel());
+
+        assert_eq!(rx.recv(), Err(RecvError::Disconnected));
+        closer.join();
+    });
+}
+
+#[test]
+fn close_keeps_queued_messages() {
+    model(|| {
+        let (tx, mut rx) = sync_channel(2);
+        let sender = spawn(move || {
+            tx.send(1).unwrap();
+            tx.close_channel();
+            tx.send(2)
+        });
+
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Err(RecvError::Disconnected));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        assert_eq!(sender.join(), Err(SendError::Disconnected(2)));
+    });
+}
+
+#[test]
+fn reciever_close_still_drains() {
+    model(|| {
+        let (tx, mut rx) = channel();
+        tx.send(1).unwrap();
+        tx.send(2).unwrap();
+        // 2 moves into the buffer
+        assert_eq!(rx.recv(), Ok(1));
+        tx.send(3).unwrap();
+        assert!(!tx.is_closed() && !rx.is_closed());
+
+        rx.close();
+        assert!(tx.is_closed() && rx.is_closed());
+        assert_eq!(tx.send(4), Err(SendError::Disconnected(4)));
+        assert_eq!(rx.recv(), Ok(2));
+        assert_eq!(rx.recv(), Ok(3));
+        assert_eq!(rx.recv(), Err(RecvError::Disconnected));
+    });
+}
+
+#[test]
+fn close_channel_closes_for_every_sender() {
+    model(|| {
+        let (tx, rx) = channel::<i32>();
+        let tx2 = tx.clone();
+        tx.close_channel();
+        assert!(tx2.is_closed() && rx.is_closed());
+        assert_eq!(tx2.send(1), Err(SendError::Disconnected(1)));
+
+        // Running out of senders closes the reciever's end too
+        let (tx, rx) = channel::<i32>();
+        drop(tx);
+        assert!(rx.is_closed());
+    });
+}
+
+#[test]
+fn close_and_drain_loses_nothing() {
+    model(|| {
+        let (tx, mut rx) = sync_channel(0);
+        let sender = spawn(move || tx.send(1));
+
+        let drained = rx.close_and_drain();
+        // The message either made it into the channel and was drained, or the sender got it back
+        match sender.join() {
+            Ok(()) => assert_eq!(drained, [1]),
+            Err(err) => {
+                assert_eq!(err, SendError::Disconnected(1));
+                assert!(drained.is_empty());
+            }
+        }
+    });
+}
+
+#[test]
+fn try_recv_and_try_iter_never_block() {
+    model(|| {
+        let (tx, mut rx) = channel();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(rx.try_iter().next(), None);
+
+        for i in 0..4 {
+            tx.send(i).unwrap();
+        }
+        // 1 to 3 move into the buffer
+        assert_eq!(rx.try_recv(), Ok(0));
+        tx.send(4).unwrap();
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [1, 2, 3, 4]);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+
+        tx.send(5).unwrap();
+        drop(tx);
+        // Whatever was sent before the last sender went away still comes first
+        assert_eq!(rx.try_recv(), Ok(5));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    });
+}
+
+#[test]
+fn recv_timeout_without_sender_activity() {
+    model(|| {
+        let (tx, mut rx) = channel::<i32>();
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(1)),
+            Err(RecvTimeoutError::Timeout)
+        );
+        drop(tx);
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(1)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+    });
+}
+
+#[test]
+fn send_error_hands_the_message_back() {
+    // Neither Debug nor Clone, the error has to work without them
+    struct Payload(i32);
+
+    let (tx, rx) = channel();
+    drop(rx);
+    let err = tx.send(Payload(1)).unwrap_err();
+    assert_eq!(format!("{err:?}"), "Disconnected(..)");
+    assert_eq!(err.to_string(), "sending on a closed channel");
+    assert_eq!(err.into_inner().0, 1);
+
+    let err: Box<dyn std::error::Error> = Box::new(tx.send(Payload(2)).unwrap_err());
+    assert_eq!(err.to_string(), "sending on a closed channel");
+    assert_eq!(
+        RecvError::Disconnected.to_string(),
+        "receiving on a closed channel"
+    );
+}
+
+#[test]
+fn recv_deadline() {
+    let (tx, mut rx) = channel();
+    // A deadline in the past still checks for messages, but never waits
+    assert_eq!(
+        rx.recv_deadline(Instant::now()),
+        Err(RecvTimeoutError::Timeout)
+    );
+    tx.send(1).unwrap();
+    assert_eq!(rx.recv_deadline(Instant::now()), Ok(1));
+
+    let started = Instant::now();
+    assert_eq!(
+        rx.recv_deadline(started + Duration::from_millis(20)),
+        Err(RecvTimeoutError::Timeout)
+    );
+    assert!(started.elapsed() >= Duration::from_millis(20));
+
+    let sender = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        tx.send(2).unwrap();
+    });
+    let deadline = Instant::now() + Duration::from_secs(10);
+    assert_eq!(rx.recv_deadline(deadline), Ok(2));
+    assert_eq!(
+        rx.recv_deadline(deadline),
+        Err(RecvTimeoutError::Disconnected)
+    );
+    sender.join().unwrap();
+}
+
+#[test]
+fn stats_follow_the_buffer() {
+    let (tx, mut rx) = Builder::new().metrics(true).build();
+    for i in 1..=3 {
+        tx.send(i).unwrap();
+    }
+    assert_eq!(rx.recv(), Ok(1)); // From the queue, 2 and 3 move into the buffer
+    assert_eq!(rx.recv(), Ok(2)); // From the buffer
+
+    let stats = tx.stats().unwrap();
+    assert_eq!((stats.sent, stats.received), (3, 2));
+    assert_eq!((stats.cache_misses, stats.cache_hits), (1, 1));
+    assert_eq!(stats.cache_hit_ratio(), 0.5);
+    assert_eq!((stats.queue_depth, stats.buffer_depth), (0, 1));
+    assert_eq!(stats.high_water_mark, 3);
+
+    // A dropped clone puts its buffer back into the queue
+    let mut rx2 = rx.clone();
+    drop(rx);
+    let stats = rx2.stats().unwrap();
+    assert_eq!((stats.queue_depth, stats.buffer_depth), (1, 0));
+
+    tx.send(4).unwrap();
+    assert_eq!(rx2.recv(), Ok(3));
+    assert_eq!(rx2.stats().unwrap().buffer_depth, 1);
+    // What close_and_drain() hands out isn't counted as received, but it's no longer buffered either
+    assert_eq!(rx2.close_and_drain(), [4]);
+    let stats = tx.stats().unwrap();
+    assert_eq!((stats.sent, stats.received), (4, 3));
+    assert_eq!((stats.queue_depth, stats.buffer_depth), (0, 0));
+    assert_eq!(stats.high_water_mark, 3);
+    assert_eq!(stats.blocking_waits, 0);
+
+    assert_eq!(channel::<i32>().0.stats(), None);
+}
+
+// Plain test, a busy spinning reciever never gives the model a point to switch threads at
+#[test]
+fn busy_spinning_reciever_never_parks() {
+    let (tx, mut rx) = Builder::new()
+        .metrics(true)
+        .wait_strategy(WaitStrategy::BusySpin)
+        .build();
+    assert_eq!(
+        rx.recv_timeout(Duration::from_millis(10)),
+        Err(RecvTimeoutError::Timeout)
+    );
+
+    let sender = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        tx.send(1).unwrap();
+        thread::sleep(Duration::from_millis(10));
+        tx.send(2).unwrap();
+    });
+    assert_eq!(rx.recv(), Ok(1));
+    assert_eq!(rx.recv(), Ok(2));
+    assert_eq!(rx.recv(), Err(RecvError::Disconnected));
+    sender.join().unwrap();
+    assert_eq!(rx.stats().unwrap().blocking_waits, 0);
+}
+
+// The async API, mixed with blocking ends
+
+#[test]
+fn async_sender_waits_for_room_in_bounded_channel() {
+    let (tx, mut rx) = sync_channel(1);
+    let sender = thread::spawn(move || {
+        block_on(async {
+            for i in 0..5 {
+                tx.send_async(i).await.unwrap();
+            }
+        })
+    });
+
+    for i in 0..5 {
+        assert_eq!(rx.recv(), Ok(i));
+    }
+    assert_eq!(rx.recv(), Err(RecvError::Disconnected));
+    sender.join().unwrap();
+}
+
+#[test]
+fn async_rendezvous_handoff_and_take_back() {
+    let (tx, mut rx) = sync_channel(0);
+    let sender = thread::spawn(move || {
+        block_on(tx.send_async(1)).unwrap();
+        block_on(tx.send_async(2))
+    });
+
+    thread::sleep(Duration::from_millis(10));
+    assert_eq!(rx.recv(), Ok(1));
+    // By now 2 is (most likely) sitting in the channel waiting for us. Dropping the reciever has to hand it back rather than lose it
+    thread::sleep(Duration::from_millis(10));
+    drop(rx);
+    assert_eq!(sender.join().unwrap(), Err(SendError::Disconnected(2)));
+}
+
+#[test]
+fn async_reciever_with_blocking_sender() {
+    let (tx, mut rx) = sync_channel(2);
+    let sender = thread::spawn(move || {
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+    });
+
+    let received = block_on(async {
+        let mut received = Vec::new();
+        while let Ok(val) = rx.recv_async().await {
+            received.push(val);
+        }
+        received
+    });
+    assert_eq!(received, [0, 1, 2, 3, 4]);
+    sender.join().unwrap();
+}
+
+#[test]
+fn closed_async_wakes_up_on_close() {
+    let (tx, rx) = channel::<i32>();
+    let mut cx = Context::from_waker(Waker::noop());
+    assert_eq!(
+        Pin::new(&mut tx.closed_async()).poll(&mut cx),
+        Poll::Pending
+    );
+
+    let closer = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        rx.close();
+        rx
+    });
+    block_on(tx.closed_async());
+    assert!(tx.is_closed());
+    // Already closed, so it's ready right away
+    assert_eq!(
+        Pin::new(&mut tx.closed_async()).poll(&mut cx),
+        Poll::Ready(())
+    );
+    drop(closer.join().unwrap());
+}
+
+#[test]
+fn poll_recv_ends_like_a_stream() {
+    let (tx, mut rx) = channel();
+    let mut cx = Context::from_waker(Waker::noop());
+    assert_eq!(rx.poll_recv(&mut cx), Poll::Pending);
+    tx.send(1).unwrap();
+    assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(Some(1)));
+    drop(tx);
+    assert_eq!(rx.poll_recv(&mut cx), Poll::Ready(None));
+}
+
+// Panics while holding the channel lock, the way a panicking waker clone in poll_recv() would
+fn poison<T>(rx: &Reciever<T>) {
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        let _channel = rx.shared.lock();
+        panic!("poisoning the channel");
+    }));
+    assert!(result.is_err());
+}
+
+#[test]
+fn poisoned_channel_recovers_by_default() {
+    let (tx, mut rx) = channel();
+    tx.send(1).unwrap();
+    poison(&rx);
+
+    tx.send(2).unwrap();
+    assert_eq!(rx.recv(), Ok(1));
+    assert_eq!(rx.recv(), Ok(2));
+    drop(tx);
+    assert_eq!(rx.recv(), Err(RecvError::Disconnected));
+}
+
+#[test]
+fn poisoned_channel_fails_when_asked_to() {
+    let (tx, mut rx) = Builder::new().poison_policy(PoisonPolicy::Fail).build();
+    tx.send(1).unwrap();
+    poison(&rx);
+
+    assert_eq!(tx.send(2), Err(SendError::Poisoned(2)));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Poisoned));
+    assert_eq!(rx.recv(), Err(RecvError::Poisoned));
+    // Neither end may panic on the way out
+    drop(tx);
+    drop(rx);
+}
+
+#[test]
+fn poisoned_watch_and_broadcast_still_work_and_drop() {
+    let (tx, rx) = watch::channel(0);
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        let _value = tx.borrow();
+        panic!("poisoning the channel");
+    }));
+    assert!(result.is_err());
+    tx.send(1).unwrap();
+    assert_eq!(*rx.borrow(), 1);
+    drop(rx);
+    drop(tx);
+
+    // The user's clone() runs under the broadcast channel's lock
+    struct PanicOnClone;
+    impl Clone for PanicOnClone {
+        fn clone(&self) -> Self {
+            panic!("poisoning the channel");
+        }
+    }
+    let (tx, mut rx) = broadcast::channel(4);
+    tx.send(PanicOnClone).unwrap();
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| rx.try_recv()));
+    assert!(result.is_err());
+    assert_eq!(tx.send(PanicOnClone).ok(), Some(1));
+    drop(rx);
+    drop(tx);
+}
+
+// Panics when dropped if armed. Clones are never armed, so only the original can blow up
+struct Bomb(bool);
+
+impl Clone for Bomb {
+    fn clone(&self) -> Self {
+        Bomb(false)
+    }
+}
+
+impl Drop for Bomb {
+    fn drop(&mut self) {
+        if self.0 {
+            panic!("dropping an armed bomb");
+        }
+    }
+}
+
+#[test]
+fn panicking_drop_leaves_watch_and_broadcast_consistent() {
+    let (tx, rx) = watch::channel(Bomb(true));
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| tx.send(Bomb(false))));
+    assert!(result.is_err());
+    // The new value made it in, along with its version
+    assert!(!rx.borrow().0);
+    assert_eq!(rx.has_changed(), Ok(true));
+
+    let (tx, mut rx) = broadcast::channel(1);
+    tx.send(Bomb(true)).unwrap();
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| tx.send(Bomb(false))));
+    assert!(result.is_err());
+    assert_eq!(
+        rx.try_recv().err(),
+        Some(broadcast::TryRecvError::Lagged(1))
+    );
+    assert!(matches!(rx.try_recv(), Ok(Bomb(false))));
+    assert_eq!(rx.try_recv().err(), Some(broadcast::TryRecvError::Empty));
+}
+
+// Select
+
+#[test]
+fn select_picks_the_ready_reciever() {
+    let (numbers_tx, mut numbers) = channel::<i32>();
+    let (words_tx, mut words) = channel::<&str>();
+
+    words_tx.send("hello").unwrap();
+    let picked = select! {
+        recv(numbers) -> msg => format!("number {msg:?}"),
+        recv(words) -> msg => format!("word {msg:?}"),
+    };
+    assert_eq!(picked, r#"word Ok("hello")"#);
+
+    numbers_tx.send(1).unwrap();
+    let picked = select! {
+        recv(numbers) -> msg => format!("number {msg:?}"),
+        recv(words) -> msg => format!("word {msg:?}"),
+    };
+    assert_eq!(picked, "number Ok(1)");
+}
+
+#[test]
+fn select_wakes_up_on_send_and_disconnect() {
+    let (tx, mut rx) = channel::<i32>();
+    let (idle_tx, mut idle) = channel::<i32>();
+
+    let sender = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        tx.send(1).unwrap();
+        thread::sleep(Duration::from_millis(10));
+    });
+    let picked = select! {
+        recv(idle) -> msg => (0, msg),
+        recv(rx) -> msg => (1, msg),
+    };
+    assert_eq!(picked, (1, Ok(1)));
+
+    // Once the sender is gone the reciever is ready for good, with the disconnect
+    sender.join().unwrap();
+    let picked = select! {
+        recv(idle) -> msg => (0, msg),
+        recv(rx) -> msg => (1, msg),
+    };
+    assert_eq!(picked, (1, Err(RecvError::Disconnected)));
+    drop(idle_tx);
+}
+
+#[test]
+fn select_default_arms() {
+    let (tx, mut rx) = channel::<i32>();
+
+    let picked = select! {
+        recv(rx) -> msg => msg.ok(),
+        default => None,
+    };
+    assert_eq!(picked, None);
+
+    let started = Instant::now();
+    let picked = select! {
+        recv(rx) -> msg => msg.ok(),
+        default(Duration::from_millis(10)) => None,
+    };
+    assert_eq!(picked, None);
+    assert!(started.elapsed() >= Duration::from_millis(10));
+
+    // A timeout too big for an Instant waits forever instead of overflowing
+    tx.send(1).unwrap();
+    let picked = select! {
+        recv(rx) -> msg => msg.ok(),
+        default(Duration::MAX) => None,
+    };
+    assert_eq!(picked, Some(1));
+}
+
+#[test]
+fn select_try_ready() {
+    let (tx, first) = channel::<i32>();
+    let (_tx2, second) = channel::<i32>();
+    let mut sel = Select::new();
+    let first_index = sel.recv(&first);
+    sel.recv(&second);
+
+    assert_eq!(sel.try_ready(), Err(TrySelectError));
+    tx.send(1).unwrap();
+    assert_eq!(sel.try_ready(), Ok(first_index));
+}
+
+// Lock-free
+
+#[test]
+fn lockfree_keeps_each_producers_order() {
+    let (tx, mut rx) = lockfree::channel();
+    let producers: Vec<_> = (0..4)
+        .map(|producer| {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                for i in 0..1000 {
+                    tx.send((producer, i)).unwrap();
+                }
+            })
+        })
+        .collect();
+    drop(tx);
+
+    let mut next = [0; 4];
+    for (producer, i) in &mut rx {
+        assert_eq!(i, next[producer]);
+        next[producer] += 1;
+    }
+    assert_eq!(next, [1000; 4]);
+    for producer in producers {
+        producer.join().unwrap();
+    }
+}
+
+#[test]
+fn lockfree_wraps_across_blocks() {
+    let (tx, mut rx) = lockfree::channel();
+    // Enough to fill several blocks before anything is received, then keep the head and tail crossing block boundaries at different times
+    for i in 0..100 {
+        tx.send(i).unwrap();
+    }
+    for i in 0..100 {
+        assert_eq!(rx.try_recv(), Ok(i));
+        tx.send(100 + i).unwrap();
+    }
+    for i in 100..200 {
+        assert_eq!(rx.try_recv(), Ok(i));
+    }
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+}
+
+#[test]
+fn lockfree_empty_disconnected_and_timeout() {
+    let (tx, mut rx) = lockfree::channel::<i32>();
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    assert_eq!(
+        rx.recv_timeout(Duration::from_millis(10)),
+        Err(RecvTimeoutError::Timeout)
+    );
+
+    let sender = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        tx.send(1).unwrap();
+    });
+    assert_eq!(rx.recv_timeout(Duration::from_secs(10)), Ok(1));
+    sender.join().unwrap();
+
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    assert_eq!(rx.recv(), Err(RecvError::Disconnected));
+    assert_eq!(
+        rx.recv_timeout(Duration::from_millis(10)),
+        Err(RecvTimeoutError::Disconnected)
+    );
+}
+
+// Counts its drops, to check that a message left in a channel is dropped exactly once
+struct Counted(Arc<AtomicUsize>);
+
+impl Drop for Counted {
+    fn drop(&mut self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn lockfree_drops_what_was_never_received() {
+    let dropped = Arc::new(AtomicUsize::new(0));
+    let (tx, mut rx) = lockfree::channel();
+    for _ in 0..70 {
+        tx.send(Counted(Arc::clone(&dropped))).unwrap();
+    }
+    // Leave the head in the middle of a block, with more full blocks after it
+    for _ in 0..40 {
+        rx.recv().unwrap();
+    }
+    assert_eq!(dropped.load(Ordering::Relaxed), 40);
+
+    drop(rx);
+    let rejected = tx.send(Counted(Arc::clone(&dropped)));
+    assert!(matches!(rejected, Err(SendError::Disconnected(_))));
+    drop(rejected);
+    drop(tx);
+    assert_eq!(dropped.load(Ordering::Relaxed), 71);
+}
+
+// Oneshot
+
+#[test]
+fn oneshot_send_then_recv() {
+    let (tx, rx) = oneshot::channel();
+    tx.send(1).unwrap();
+    assert_eq!(rx.recv(), Ok(1));
+
+    let (tx, rx) = oneshot::channel();
+    let sender = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        tx.send(2).unwrap();
+    });
+    assert_eq!(rx.recv(), Ok(2));
+    sender.join().unwrap();
+}
+
+#[test]
+fn oneshot_async_recv() {
+    let (tx, mut rx) = oneshot::channel();
+    // Poll once with some other waker first, so that block_on() has to take the waker slot back and swap in its own
+    let mut cx = Context::from_waker(Waker::noop());
+    assert_eq!(Pin::new(&mut rx).poll(&mut cx), Poll::Pending);
+
+    let sender = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        tx.send(1).unwrap();
+    });
+    assert_eq!(block_on(rx), Ok(1));
+    sender.join().unwrap();
+}
+
+#[test]
+fn oneshot_sender_dropped_without_sending() {
+    let (tx, mut rx) = oneshot::channel::<i32>();
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    drop(tx);
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    assert_eq!(rx.recv(), Err(RecvError::Disconnected));
+
+    let (tx, rx) = oneshot::channel::<i32>();
+    let sender = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        drop(tx);
+    });
+    assert_eq!(block_on(rx), Err(RecvError::Disconnected));
+    sender.join().unwrap();
+}
+
+#[test]
+fn oneshot_reciever_dropped_before_send() {
+    let (tx, rx) = oneshot::channel();
+    drop(rx);
+    assert_eq!(tx.send(1), Err(SendError::Disconnected(1)));
+}
+
+#[test]
+fn oneshot_recv_timeout() {
+    let (tx, mut rx) = oneshot::channel();
+    assert_eq!(
+        rx.recv_timeout(Duration::from_millis(10)),
+        Err(RecvTimeoutError::Timeout)
+    );
+    tx.send(1).unwrap();
+    assert_eq!(rx.recv_timeout(Duration::from_millis(10)), Ok(1));
+}
+
+#[test]
+fn oneshot_drops_the_value_exactly_once() {
+    let dropped = Arc::new(AtomicUsize::new(0));
+
+    // Sent but never received
+    let (tx, rx) = oneshot::channel();
+    tx.send(Counted(Arc::clone(&dropped))).unwrap();
+    drop(rx);
+    assert_eq!(dropped.load(Ordering::Relaxed), 1);
+
+    // Handed back to the sender
+    let (tx, rx) = oneshot::channel();
+    drop(rx);
+    let rejected = tx.send(Counted(Arc::clone(&dropped)));
+    assert_eq!(dropped.load(Ordering::Relaxed), 1);
+    drop(rejected);
+    assert_eq!(dropped.load(Ordering::Relaxed), 2);
+
+    // Received, so it's up to the reciever to drop it
+    let (tx, rx) = oneshot::channel();
+    tx.send(Counted(Arc::clone(&dropped))).unwrap();
+    let received = rx.recv();
+    assert_eq!(dropped.load(Ordering::Relaxed), 2);
+    drop(received);
+    assert_eq!(dropped.load(Ordering::Relaxed), 3);
+}
+
+// Broadcast
+
+#[test]
+fn broadcast_fans_out_to_every_subscriber() {
+    let (tx, mut rx) = broadcast::channel(4);
+    let mut rx2 = tx.subscribe();
+    assert_eq!(tx.send(1), Ok(2));
+    assert_eq!(tx.send(2), Ok(2));
+
+    for rx in [&mut rx, &mut rx2] {
+        assert_eq!(rx.recv(), Ok(1));
+        assert_eq!(rx.recv(), Ok(2));
+        assert_eq!(rx.try_recv(), Err(broadcast::TryRecvError::Empty));
+    }
+}
+
+#[test]
+fn broadcast_lagged_reciever_resumes_at_the_oldest_message() {
+    let (tx, mut rx) = broadcast::channel(2);
+    for i in 1..=5 {
+        tx.send(i).unwrap();
+    }
+
+    assert_eq!(rx.recv(), Err(broadcast::RecvError::Lagged(3)));
+    assert_eq!(rx.recv(), Ok(4));
+    assert_eq!(rx.recv(), Ok(5));
+    assert_eq!(rx.try_recv(), Err(broadcast::TryRecvError::Empty));
+}
+
+#[test]
+fn broadcast_subscriber_only_sees_later_messages() {
+    let (tx, _rx) = broadcast::channel(4);
+    tx.send(1).unwrap();
+    let mut late = tx.subscribe();
+    assert_eq!(late.try_recv(), Err(broadcast::TryRecvError::Empty));
+    tx.send(2).unwrap();
+    assert_eq!(late.try_recv(), Ok(2));
+    assert_eq!(late.try_recv(), Err(broadcast::TryRecvError::Empty));
+}
+
+#[test]
+fn broadcast_closed_after_last_sender_drop() {
+    let (tx, mut rx) = broadcast::channel(4);
+    let tx2 = tx.clone();
+    tx.send(1).unwrap();
+    drop(tx);
+    assert_eq!(rx.recv(), Ok(1));
+    assert_eq!(rx.try_recv(), Err(broadcast::TryRecvError::Empty));
+
+    // Wakes up a reciever that is already waiting
+    let sender = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        drop(tx2);
+    });
+    assert_eq!(rx.recv(), Err(broadcast::RecvError::Closed));
+    assert_eq!(rx.try_recv(), Err(broadcast::TryRecvError::Closed));
+    sender.join().unwrap();
+}
+
+// Watch
+
+#[test]
+fn watch_changed_blocks_until_a_send() {
+    let (tx, mut rx) = watch::channel(0);
+    let sender = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        tx.send(1).unwrap();
+        tx
+    });
+    assert_eq!(rx.changed(), Ok(()));
+    assert_eq!(*rx.borrow(), 1);
+    // changed() marked it as seen
+    assert_eq!(rx.has_changed(), Ok(false));
+    drop(sender.join().unwrap());
+}
+
+#[test]
+fn watch_borrow_and_update_marks_the_value_seen() {
+    let (tx, mut rx) = watch::channel(0);
+    assert_eq!(rx.has_changed(), Ok(false));
+    tx.send(1).unwrap();
+    tx.send(2).unwrap();
+    assert_eq!(rx.has_changed(), Ok(true));
+    // borrow() doesn't count as seeing it, borrow_and_update() does
+    assert_eq!(*rx.borrow(), 2);
+    assert_eq!(rx.has_changed(), Ok(true));
+    assert_eq!(*rx.borrow_and_update(), 2);
+    assert_eq!(rx.has_changed(), Ok(false));
+
+    // A new subscriber has seen the current value already
+    let late = tx.subscribe();
+    assert_eq!(late.has_changed(), Ok(false));
+    tx.send(3).unwrap();
+    assert_eq!(late.has_changed(), Ok(true));
+}
+
+#[test]
+fn watch_sender_drop_wakes_up_changed() {
+    let (tx, mut rx) = watch::channel(0);
+    let sender = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(10));
+        drop(tx);
+    });
+    assert_eq!(rx.changed(), Err(RecvError::Disconnected));
+    assert_eq!(rx.has_changed(), Err(RecvError::Disconnected));
+    // The last value is still there to look at
+    assert_eq!(*rx.borrow(), 0);
+    sender.join().unwrap();
+}
+
+// Priority
+
+#[test]
+fn priority_delivers_urgent_first_and_fifo_within_a_level() {
+    let (tx, mut rx) = priority::channel();
+    tx.send_with_priority("a", 1).unwrap();
+    tx.send("b").unwrap();
+    tx.send_with_priority("c", 1).unwrap();
+    tx.send("d").unwrap();
+    drop(tx);
+
+    assert_eq!(rx.by_ref().collect::<Vec<_>>(), ["a", "c", "b", "d"]);
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+}
+
+#[test]
+fn priority_aging_lets_old_messages_through() {
+    let (tx, mut rx) = priority::channel_with_aging(Duration::from_millis(10));
+    tx.send("old").unwrap();
+    // Enough for "old" to gain 5 levels, however long the rest of the test takes, it stays 5 levels ahead of the ones sent below
+    thread::sleep(Duration::from_millis(50));
+    tx.send_with_priority("new", 3).unwrap();
+    tx.send_with_priority("newer", 3).unwrap();
+
+    assert_eq!(rx.recv(), Ok("old"));
+    assert_eq!(rx.recv(), Ok("new"));
+    assert_eq!(rx.recv(), Ok("newer"));
+}
+
+#[test]
+fn priority_buffer_gives_way_to_urgent_messages() {
+    let (tx, mut rx) = priority::channel();
+    tx.send("a").unwrap();
+    tx.send("b").unwrap();
+    // Takes "a" and moves "b" into the buffer
+    assert_eq!(rx.recv(), Ok("a"));
+
+    tx.send_with_priority("urgent", 5).unwrap();
+    assert_eq!(rx.recv(), Ok("urgent"));
+    assert_eq!(rx.recv(), Ok("b"));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+}
@@ -0,0 +1,183 @@
+// Watch channel: only the latest value is kept. The sender overwrites a single slot and bumps its version, and recievers either look at whatever is current with borrow() or block in changed() until the version moves past the last one they saw. Useful for state like the current config, where a backlog of old values is of no use
+use std::{
+    ops::Deref,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{RecvError, SendError};
+
+// Sender
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut inner = self.shared.lock();
+        inner.is_sender_alive = false;
+        drop(inner);
+        // Recievers waiting in changed() have to find out that no new value is ever coming
+        self.shared.changed.notify_all();
+    }
+}
+
+impl<T> Sender<T> {
+    // Replaces the current value. Fails (handing the value back) if there is nobody left to see it
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        let mut channel = self.shared.lock();
+        if channel.receivers_count == 0 {
+            return Err(SendError::Disconnected(value));
+        }
+
+        let old = std::mem::replace(&mut channel.value, value);
+        channel.version += 1;
+        drop(channel);
+        self.shared.changed.notify_all();
+        // Its Drop may panic, so only once the new value is in place and the lock released
+        drop(old);
+        Ok(())
+    }
+
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref {
+            inner: self.shared.lock(),
+        }
+    }
+
+    // Creates a new reciever. The current value counts as already seen by it
+    pub fn subscribe(&self) -> Reciever<T> {
+        let mut inner = self.shared.lock();
+        inner.receivers_count += 1;
+
+        Reciever {
+            shared: Arc::clone(&self.shared),
+            seen: inner.version,
+        }
+    }
+}
+
+// Reciever
+pub struct Reciever<T> {
+    shared: Arc<Shared<T>>,
+    seen: u64, // Version of the last value this reciever has seen
+}
+
+// A clone has seen the same version as the original
+impl<T> Clone for Reciever<T> {
+    fn clone(&self) -> Self {
+        let mut inner = self.shared.lock();
+        inner.receivers_count += 1;
+
+        Self {
+            shared: Arc::clone(&self.shared),
+            seen: self.seen,
+        }
+    }
+}
+
+impl<T> Drop for Reciever<T> {
+    fn drop(&mut self) {
+        let mut inner = self.shared.lock();
+        inner.receivers_count -= 1;
+    }
+}
+
+impl<T> Reciever<T> {
+    // Returns the current value without marking it as seen. The channel stays locked while the Ref is alive, so don't hold on to it
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref {
+            inner: self.shared.lock(),
+        }
+    }
+
+    // Same as borrow() but also marks the value as seen, so changed() only returns once something newer is sent
+    pub fn borrow_and_update(&mut self) -> Ref<'_, T> {
+        let inner = self.shared.lock();
+        self.seen = inner.version;
+        Ref { inner }
+    }
+
+    // Whether a value newer than the last seen one is available. Fails once the sender is gone
+    pub fn has_changed(&self) -> Result<bool, RecvError> {
+        let inner = self.shared.lock();
+        if !inner.is_sender_alive {
+            return Err(RecvError::Disconnected);
+        }
+        Ok(inner.version != self.seen)
+    }
+
+    // Blocks until a value newer than the last seen one is sent and marks it as seen. Read it with borrow() afterwards. Fails if the sender goes away before sending anything new
+    pub fn changed(&mut self) -> Result<(), RecvError> {
+        let mut channel = self.shared.lock();
+        // Loop for the same reason as in the regular channel's recv(): the thread may wake up without anything having changed
+        loop {
+            if channel.version != self.seen {
+                self.seen = channel.version;
+                return Ok(());
+            }
+            if !channel.is_sender_alive {
+                return Err(RecvError::Disconnected);
+            }
+            channel = self
+                .shared
+                .changed
+                .wait(channel)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+}
+
+// Read access to the current value. Holds the channel's lock, so the sender can't replace the value while it is being looked at
+pub struct Ref<'a, T> {
+    inner: MutexGuard<'a, Inner<T>>,
+}
+
+impl<T> Deref for Ref<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner.value
+    }
+}
+
+// Channel
+struct Shared<T> {
+    inner: Mutex<Inner<T>>,
+    changed: Condvar,
+}
+
+impl<T> Shared<T> {
+    // Whoever holds a Ref runs their own code under the lock, but can only read the value. Replaced values are dropped after the lock is released, so a panic can't leave a send half done and a poisoned lock is still good to use. Drop must never panic on it
+    fn lock(&self) -> MutexGuard<'_, Inner<T>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+struct Inner<T> {
+    value: T,
+    version: u64, // Bumped on every send, the initial value is version 0
+    is_sender_alive: bool,
+    receivers_count: usize,
+}
+
+pub fn channel<T>(initial: T) -> (Sender<T>, Reciever<T>) {
+    let inner = Inner {
+        value: initial,
+        version: 0,
+        is_sender_alive: true,
+        receivers_count: 1,
+    };
+
+    let shared = Arc::new(Shared {
+        inner: Mutex::new(inner),
+        changed: Condvar::new(),
+    });
+
+    let sender = Sender {
+        shared: shared.clone(),
+    };
+
+    let receiver = Reciever { shared, seen: 0 };
+
+    (sender, receiver)
+}